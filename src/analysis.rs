@@ -0,0 +1,635 @@
+use crate::{
+    channel::Channel,
+    error::AnalysisError,
+    flight::{Flight, Record, INITIAL_VALUE},
+    header::Alarms,
+};
+
+/// Manifold pressure, in inches of mercury, at which rated power is made.
+const RATED_MAP: f64 = 29.92;
+/// Engine speed at which rated power is made.
+const RATED_RPM: f64 = 2700.0;
+/// Standard day temperature in degrees Rankine (59°F).
+const STANDARD_TEMP_R: f64 = 518.67;
+
+/// Approximate engine power for one record.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerEstimate {
+    /// Percent of rated power.
+    pub percent: f64,
+    pub horsepower: f64,
+}
+
+/// Estimates power from MAP, RPM and OAT for installs that do not record it.
+///
+/// Power is taken as proportional to manifold pressure and engine speed,
+/// relative to full throttle at rated RPM, and corrected for air density
+/// by the square root of the standard to actual temperature ratio. OAT is
+/// assumed to be in Fahrenheit. Returns `None` unless all three are recorded.
+pub fn estimate_power(record: &Record, engine_rated_hp: f64) -> Option<PowerEstimate> {
+    let map = record.map?;
+    let rpm = f64::from(record.rpm?);
+    let oat_r = f64::from(record.oat?) + 459.67;
+    if oat_r <= 0.0 {
+        return None;
+    }
+    let fraction = (map / RATED_MAP) * (rpm / RATED_RPM) * (STANDARD_TEMP_R / oat_r).sqrt();
+    Some(PowerEstimate {
+        percent: fraction * 100.0,
+        horsepower: fraction * engine_rated_hp,
+    })
+}
+
+/// Difference between the hottest and coolest EGT in a record.
+///
+/// A probe that is not connected would inflate the spread, so the
+/// `disconnected` probes, as found by `disconnected_probes` over the
+/// record's flight, are skipped.
+pub fn egt_spread(record: &Record, disconnected: &[Channel]) -> Option<i32> {
+    let readings = (1..=record.egt.len() as u8)
+        .zip(&record.egt)
+        .filter(|(n, _)| !disconnected.contains(&Channel::Egt(*n)))
+        .map(|(_, egt)| *egt)
+        .collect::<Vec<i32>>();
+    Some(readings.iter().max()? - readings.iter().min()?)
+}
+
+/// The probes that look disconnected over `records`, normally a whole
+/// flight: channels that only ever read zero or the value every field
+/// starts at. A working probe can pass through either, 240° being an
+/// ordinary CHT, so it is the channel as a whole that is left out, never a
+/// single reading.
+pub fn disconnected_probes(records: &[Record]) -> Vec<Channel> {
+    let mut channels = records
+        .iter()
+        .flat_map(Record::channels)
+        .collect::<Vec<Channel>>();
+    channels.sort();
+    channels.dedup();
+    channels.retain(|channel| {
+        records
+            .iter()
+            .filter_map(|r| r.get(*channel))
+            .all(|v| v == 0.0 || v == f64::from(INITIAL_VALUE))
+    });
+    channels
+}
+
+/// Merges records into buckets of `bucket_secs` seconds from the first
+/// timestamp, for plotting long flights.
+///
+/// Temperatures keep their peak over the bucket, flows, pressures, volts
+/// and RPM are averaged, and fuel used is the last totalizer reading. Each
+/// bucket is stamped with the time of its first record. Records without a
+/// timestamp are passed through on their own.
+pub fn downsample(records: &[Record], bucket_secs: i64) -> Vec<Record> {
+    if bucket_secs <= 0 {
+        return records.to_vec();
+    }
+    let start = records.iter().find_map(|r| r.timestamp);
+    let bucket = |record: &Record| {
+        record
+            .timestamp
+            .zip(start)
+            .map(|(ts, start)| (ts - start).div_euclid(bucket_secs))
+    };
+    records
+        .chunk_by(|a, b| bucket(a).is_some() && bucket(a) == bucket(b))
+        .map(merge)
+        .collect()
+}
+
+fn merge(bucket: &[Record]) -> Record {
+    let max = |value: fn(&Record) -> Option<i32>| bucket.iter().filter_map(value).max();
+    let mean = |value: fn(&Record) -> Option<f64>| {
+        let values = bucket.iter().filter_map(value).collect::<Vec<f64>>();
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    };
+    let cylinders = |probes: fn(&Record) -> &Vec<i32>| {
+        (0..probes(&bucket[0]).len())
+            .map(|i| {
+                bucket
+                    .iter()
+                    .filter_map(|r| probes(r).get(i).copied())
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<i32>>()
+    };
+    Record {
+        timestamp: bucket[0].timestamp,
+        egt: cylinders(|r| &r.egt),
+        cht: cylinders(|r| &r.cht),
+        tit1: max(|r| r.tit1),
+        tit2: max(|r| r.tit2),
+        oil_temp: max(|r| r.oil_temp),
+        oil_pressure: mean(|r| r.oil_pressure.map(f64::from)).map(|v| v.round() as i32),
+        cdt: max(|r| r.cdt),
+        iat: max(|r| r.iat),
+        oat: max(|r| r.oat),
+        volts: mean(|r| r.volts),
+        fuel_flow: mean(|r| r.fuel_flow),
+        fuel_used: bucket.iter().rev().find_map(|r| r.fuel_used),
+        map: mean(|r| r.map),
+        rpm: mean(|r| r.rpm.map(f64::from)).map(|v| v.round() as i32),
+        mark: bucket.iter().fold(0, |mark, r| mark | r.mark),
+    }
+}
+
+/// Lays records onto a regular grid every `interval_secs` seconds from the
+/// first timestamp to the last, for tools that want a series without gaps.
+/// Each grid time holds the latest record at or before it, carried forward
+/// over a gap with the pilot mark cleared. Records without a timestamp have
+/// no place on the grid and are left out.
+pub fn fill_gaps(records: &[Record], interval_secs: i64) -> Vec<Record> {
+    let timed = records
+        .iter()
+        .filter_map(|r| Some((r.timestamp?, r)))
+        .collect::<Vec<(i64, &Record)>>();
+    let (Some(&(start, _)), Some(&(end, _))) = (timed.first(), timed.last()) else {
+        return Vec::new();
+    };
+    if interval_secs <= 0 {
+        return timed.into_iter().map(|(_, r)| r.clone()).collect();
+    }
+    let mut grid = Vec::new();
+    let mut next = 0;
+    for time in (start..=end).step_by(interval_secs as usize) {
+        let fresh = timed.get(next).is_some_and(|(ts, _)| *ts <= time);
+        while timed.get(next).is_some_and(|(ts, _)| *ts <= time) {
+            next += 1;
+        }
+        let mut record = timed[next - 1].1.clone();
+        if !fresh {
+            record.mark &= !0x02;
+        }
+        record.timestamp = Some(time);
+        grid.push(record);
+    }
+    grid
+}
+
+/// RPM above which a record counts as cruise for `dead_cylinders`.
+const CRUISE_RPM: i32 = 2000;
+/// How far below the other cylinders, on average, a cylinder's EGT has to
+/// run before `dead_cylinders` reports it.
+const DEAD_EGT_DEFICIT: f64 = 200.0;
+/// How close to the other cylinders a cylinder's CHT has to stay for its
+/// cold EGT to be read as a misfire rather than a failed probe pair.
+const CHT_IN_FAMILY: f64 = 50.0;
+
+/// A cylinder whose exhaust runs cold while its head stays in family.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeadCylinder {
+    /// Cylinder number, starting at 1.
+    pub cylinder: u8,
+    /// Mean EGT below the other cylinders, in degrees.
+    pub egt_deficit: f64,
+    /// Mean CHT above (or below, if negative) the other cylinders.
+    pub cht_deviation: f64,
+    /// Cruise records the means are taken over.
+    pub records: usize,
+}
+
+/// Finds cylinders that look like they are not firing: a misfire or fouled
+/// plug leaves the exhaust cold while the head, heated by its neighbours
+/// and the block, stays close to the rest.
+///
+/// Only cruise records count, those above 2000 RPM, or every record if RPM
+/// is not recorded. Each cylinder is compared with the mean of the others
+/// in the same record, skipping faulted readings and the probes
+/// `disconnected_probes` finds over all of `records`. A cylinder
+/// is reported when its EGT averages more than 200° below the others and
+/// its CHT stays within 50°. At least three cylinders are needed to tell
+/// one from the rest.
+pub fn dead_cylinders(records: &[Record]) -> Vec<DeadCylinder> {
+    let cruise = records
+        .iter()
+        .filter(|r| r.rpm.is_none_or(|rpm| rpm > CRUISE_RPM))
+        .collect::<Vec<&Record>>();
+    let cylinders = cruise
+        .iter()
+        .map(|r| r.egt.len().min(r.cht.len()))
+        .max()
+        .unwrap_or(0);
+    let disconnected = disconnected_probes(records);
+    // How far one cylinder reads from the mean of the others
+    let deviation = |record: &Record, channel: fn(u8) -> Channel, cylinder: u8| {
+        let value = reading(record, channel(cylinder), &disconnected)?;
+        let others = (1..=cylinders as u8)
+            .filter(|n| *n != cylinder)
+            .filter_map(|n| reading(record, channel(n), &disconnected))
+            .collect::<Vec<f64>>();
+        (others.len() >= 2).then(|| value - others.iter().sum::<f64>() / others.len() as f64)
+    };
+    (1..=cylinders as u8)
+        .filter_map(|cylinder| {
+            let deviations = cruise
+                .iter()
+                .filter_map(|r| {
+                    Some((
+                        deviation(r, Channel::Egt, cylinder)?,
+                        deviation(r, Channel::Cht, cylinder)?,
+                    ))
+                })
+                .collect::<Vec<(f64, f64)>>();
+            let count = deviations.len();
+            if count == 0 {
+                return None;
+            }
+            let egt = deviations.iter().map(|(egt, _)| egt).sum::<f64>() / count as f64;
+            let cht = deviations.iter().map(|(_, cht)| cht).sum::<f64>() / count as f64;
+            (-egt > DEAD_EGT_DEFICIT && cht.abs() <= CHT_IN_FAMILY).then_some(DeadCylinder {
+                cylinder,
+                egt_deficit: -egt,
+                cht_deviation: cht,
+                records: count,
+            })
+        })
+        .collect()
+}
+
+/// Least fuel flow drop, in `$F` units per hour, for a steady lean-out to
+/// count as a lean sweep.
+const LEAN_SWEEP_DROP: f64 = 1.0;
+
+/// Result of `gami_spread`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GamiSpread {
+    /// Difference between the highest and lowest fuel flow at which a
+    /// cylinder peaked.
+    pub spread: f64,
+    /// Fuel flow at each cylinder's EGT peak, cylinder 1 first.
+    pub peak_fuel_flow: Vec<f64>,
+    /// Index of the first and last record of the lean sweep.
+    pub sweep: (usize, usize),
+}
+
+/// Fuel flow spread between the first and last cylinder to reach peak EGT
+/// during a lean sweep, which shows how evenly the injectors are matched.
+///
+/// The sweep is the longest run of records over which fuel flow never
+/// rises and falls by at least 1.0 in total. Each cylinder peaks at its
+/// hottest EGT in the run, the first one if it holds there. Returns `None`
+/// if there is no sweep, or a cylinder is hottest at either end of it and
+/// so never passed peak.
+pub fn gami_spread(records: &[Record]) -> Option<GamiSpread> {
+    let mut best: Option<(usize, usize)> = None;
+    let mut start = 0;
+    for end in 0..records.len() {
+        let next = records.get(end + 1).and_then(|r| r.fuel_flow);
+        let leaning = records[end]
+            .fuel_flow
+            .zip(next)
+            .is_some_and(|(ff, next)| next <= ff);
+        if leaning {
+            continue;
+        }
+        let drop = records[start].fuel_flow.zip(records[end].fuel_flow);
+        let long = best.is_none_or(|(s, e)| end - start > e - s);
+        if drop.is_some_and(|(first, last)| first - last >= LEAN_SWEEP_DROP) && long {
+            best = Some((start, end));
+        }
+        start = end + 1;
+    }
+    let (start, end) = best?;
+    let sweep = &records[start..=end];
+
+    let cylinders = sweep.iter().map(|r| r.egt.len()).min()?;
+    let peak_fuel_flow = (0..cylinders)
+        .map(|cylinder| {
+            let hottest = sweep.iter().map(|r| r.egt[cylinder]).max()?;
+            let peak = sweep.iter().position(|r| r.egt[cylinder] == hottest)?;
+            (peak > 0 && peak < sweep.len() - 1)
+                .then(|| sweep[peak].fuel_flow)
+                .flatten()
+        })
+        .collect::<Option<Vec<f64>>>()?;
+    let highest = peak_fuel_flow.iter().copied().reduce(f64::max)?;
+    let lowest = peak_fuel_flow.iter().copied().reduce(f64::min)?;
+    Some(GamiSpread {
+        spread: highest - lowest,
+        peak_fuel_flow,
+        sweep: (start, end),
+    })
+}
+
+/// A run of records over which one cylinder's EGT rose faster than the
+/// limit given to `egt_rises`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EgtRise {
+    /// Cylinder number, starting at 1.
+    pub cylinder: u8,
+    /// Index of the record the rise starts from and of its last record.
+    pub span: (usize, usize),
+    /// Fastest rise between two records of the span, in degrees a minute.
+    pub peak_rate: f64,
+}
+
+/// Finds spans where an EGT climbs faster than `max_rate` degrees a minute,
+/// as it does when leaning goes past peak too quickly or detonation sets
+/// in. It is the exhaust side of the `max_cht_cool_rate` alarm.
+///
+/// The rate is taken between each record and the one before it from their
+/// timestamps, so records without one are not checked. Faulted readings
+/// and the probes `disconnected_probes` finds are skipped. Rises are given
+/// cylinder by cylinder, in record order.
+pub fn egt_rises(records: &[Record], max_rate: f64) -> Vec<EgtRise> {
+    let cylinders = records.iter().map(|r| r.egt.len()).max().unwrap_or(0);
+    let disconnected = disconnected_probes(records);
+    let mut rises = Vec::new();
+    for cylinder in 1..=cylinders as u8 {
+        let channel = Channel::Egt(cylinder);
+        let mut current: Option<EgtRise> = None;
+        for (i, pair) in records.windows(2).enumerate() {
+            let rate = match (
+                pair[0].timestamp,
+                pair[1].timestamp,
+                reading(&pair[0], channel, &disconnected),
+                reading(&pair[1], channel, &disconnected),
+            ) {
+                (Some(then), Some(now), Some(before), Some(after)) if now > then => {
+                    Some((after - before) * 60.0 / (now - then) as f64)
+                }
+                _ => None,
+            };
+            match rate.filter(|rate| *rate > max_rate) {
+                Some(rate) => {
+                    let rise = current.get_or_insert(EgtRise {
+                        cylinder,
+                        span: (i, i + 1),
+                        peak_rate: rate,
+                    });
+                    rise.span.1 = i + 1;
+                    rise.peak_rate = rise.peak_rate.max(rate);
+                }
+                None => rises.extend(current.take()),
+            }
+        }
+        rises.extend(current);
+    }
+    rises
+}
+
+/// Widest RPM swing from the start of a run for `cruise_averages`.
+const CRUISE_RPM_BAND: i32 = 50;
+/// Widest manifold pressure swing, in inches of mercury, for
+/// `cruise_averages`.
+const CRUISE_MAP_BAND: f64 = 0.5;
+/// Fewest records a stable run needs to count as cruise.
+const MIN_CRUISE_RECORDS: usize = 10;
+
+/// Result of `cruise_averages`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CruiseAverages {
+    /// Index of the first and last record of the cruise segment.
+    pub span: (usize, usize),
+    /// Mean EGT per cylinder, cylinder 1 first, `None` for a probe with no
+    /// good reading.
+    pub egt: Vec<Option<f64>>,
+    /// Mean CHT per cylinder, as for `egt`.
+    pub cht: Vec<Option<f64>>,
+    pub fuel_flow: Option<f64>,
+}
+
+/// Mean EGT, CHT and fuel flow over the cruise segment, leaving out the
+/// climb and descent.
+///
+/// Cruise is taken as the longest run of records above 2000 RPM over which
+/// RPM stays within 50 of the run's first record and manifold pressure,
+/// when recorded, within 0.5 inHg. A full power climb can be as steady as
+/// cruise, but is shorter in all but the shortest flights. Returns `None`
+/// without RPM or if no run is at least 10 records long. Faulted readings
+/// and the probes `disconnected_probes` finds over all of `records` are
+/// skipped.
+pub fn cruise_averages(records: &[Record]) -> Option<CruiseAverages> {
+    let steady = |first: &Record, record: &Record| match (first.rpm, record.rpm) {
+        (Some(base), Some(rpm)) => {
+            base > CRUISE_RPM
+                && (rpm - base).abs() <= CRUISE_RPM_BAND
+                && first
+                    .map
+                    .zip(record.map)
+                    .is_none_or(|(base, map)| (map - base).abs() <= CRUISE_MAP_BAND)
+        }
+        _ => false,
+    };
+    let mut best: Option<(usize, usize)> = None;
+    let mut start = 0;
+    for end in 0..records.len() {
+        // A run holds steady against its first record, so it cannot drift
+        if !steady(&records[start], &records[end]) {
+            start = end;
+            if !steady(&records[end], &records[end]) {
+                start = end + 1;
+                continue;
+            }
+        }
+        if best.is_none_or(|(s, e)| end - start > e - s) {
+            best = Some((start, end));
+        }
+    }
+    let (start, end) = best.filter(|(s, e)| e - s + 1 >= MIN_CRUISE_RECORDS)?;
+    let cruise = &records[start..=end];
+    let disconnected = disconnected_probes(records);
+
+    let mean = |values: Vec<f64>| {
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    };
+    let per_cylinder = |channel: fn(u8) -> Channel, count: usize| {
+        (1..=count as u8)
+            .map(|n| {
+                mean(
+                    cruise
+                        .iter()
+                        .filter_map(|r| reading(r, channel(n), &disconnected))
+                        .collect(),
+                )
+            })
+            .collect::<Vec<Option<f64>>>()
+    };
+    let egts = cruise.iter().map(|r| r.egt.len()).max().unwrap_or(0);
+    let chts = cruise.iter().map(|r| r.cht.len()).max().unwrap_or(0);
+    Some(CruiseAverages {
+        span: (start, end),
+        egt: per_cylinder(Channel::Egt, egts),
+        cht: per_cylinder(Channel::Cht, chts),
+        fuel_flow: mean(cruise.iter().filter_map(|r| r.fuel_flow).collect()),
+    })
+}
+
+/// A reading of `channel`, unless it is one of the `disconnected` probes or
+/// looks like a fault.
+fn reading(record: &Record, channel: Channel, disconnected: &[Channel]) -> Option<f64> {
+    record
+        .get(channel)
+        .filter(|_| !disconnected.contains(&channel) && !record.is_fault(channel))
+}
+
+/// An alarm limit from `$A` that a record breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AlarmKind {
+    MinVolts,
+    MaxVolts,
+    MaxEgtSpread,
+    MaxCht,
+    MaxChtCoolRate,
+    MaxEgt,
+    MinOilTemp,
+    MaxOilTemp,
+}
+
+/// Channel names `Alarms::set_redline` accepts.
+const REDLINE_NAMES: [&str; 4] = ["CHT", "EGT", "OIL", "VOLTS"];
+
+impl Alarms {
+    /// The upper limit for `channel`: the `$A` maximum for its kind of
+    /// probe, or an override from `set_redline`.
+    pub fn redline(&self, channel: Channel) -> Option<f64> {
+        match channel {
+            Channel::Egt(_) => self.max_egt.map(f64::from),
+            Channel::Cht(_) => self.max_cht.map(f64::from),
+            Channel::OilTemp => self.max_oil_temp.map(f64::from),
+            Channel::Volts => self.max_volts,
+            _ => None,
+        }
+    }
+
+    /// Replaces a redline, for a file without `$A` limits or with wrong
+    /// ones. `name` is `CHT`, `EGT`, `OIL` or `VOLTS` in any case, and the
+    /// limit applies to every probe of that kind. Temperatures are whole
+    /// degrees, so `value` is rounded for them.
+    pub fn set_redline(&mut self, name: &str, value: f64) -> Result<(), AnalysisError> {
+        if !value.is_finite() || value <= 0.0 {
+            return Err(AnalysisError::InvalidRedline {
+                name: name.to_string(),
+                value,
+            });
+        }
+        let degrees = Some(value.round() as i32);
+        match name.to_ascii_uppercase().as_str() {
+            "CHT" => self.max_cht = degrees,
+            "EGT" => self.max_egt = degrees,
+            "OIL" => self.max_oil_temp = degrees,
+            "VOLTS" => self.max_volts = Some(value),
+            _ => {
+                return Err(AnalysisError::UnknownRedline {
+                    name: name.to_string(),
+                    available: REDLINE_NAMES.map(str::to_string).to_vec(),
+                })
+            }
+        }
+        Ok(())
+    }
+
+    /// A reading as a percentage of its `redline`, for annotating how close
+    /// a channel ran to its limit.
+    pub fn percent_of_redline(&self, record: &Record, channel: Channel) -> Option<f64> {
+        let value = record.get(channel).filter(|_| !record.is_fault(channel))?;
+        Some(value / self.redline(channel)? * 100.0)
+    }
+
+    /// The limits `record` breaks on its own, in declaration order. The CHT
+    /// cooling rate needs the record before it; see
+    /// `Flight::alarm_violations`. Readings that look like probe faults are
+    /// not checked, and the EGT spread leaves out the `disconnected` probes.
+    pub fn violations(&self, record: &Record, disconnected: &[Channel]) -> Vec<AlarmKind> {
+        let valid = |channel: Channel| record.get(channel).filter(|_| !record.is_fault(channel));
+        let probes = |channel: fn(u8) -> Channel, count: usize| {
+            (1..=count as u8)
+                .filter_map(|n| valid(channel(n)))
+                .collect::<Vec<f64>>()
+        };
+        let egts = probes(Channel::Egt, record.egt.len());
+        let chts = probes(Channel::Cht, record.cht.len());
+        let volts = valid(Channel::Volts);
+        let oil = valid(Channel::OilTemp);
+        let above = |values: &[f64], limit: Option<i32>| {
+            limit.is_some_and(|limit| values.iter().any(|v| *v > f64::from(limit)))
+        };
+
+        let mut violations = Vec::new();
+        let mut check = |broken: bool, kind: AlarmKind| {
+            if broken {
+                violations.push(kind);
+            }
+        };
+        check(
+            volts.zip(self.min_volts).is_some_and(|(v, min)| v < min),
+            AlarmKind::MinVolts,
+        );
+        check(
+            volts.zip(self.max_volts).is_some_and(|(v, max)| v > max),
+            AlarmKind::MaxVolts,
+        );
+        check(
+            egt_spread(record, disconnected)
+                .zip(self.max_egt_spread)
+                .is_some_and(|(spread, max)| spread > max),
+            AlarmKind::MaxEgtSpread,
+        );
+        check(above(&chts, self.max_cht), AlarmKind::MaxCht);
+        check(above(&egts, self.max_egt), AlarmKind::MaxEgt);
+        check(
+            oil.zip(self.min_oil_temp)
+                .is_some_and(|(v, min)| v < f64::from(min)),
+            AlarmKind::MinOilTemp,
+        );
+        check(
+            above(oil.as_slice(), self.max_oil_temp),
+            AlarmKind::MaxOilTemp,
+        );
+        violations
+    }
+}
+
+impl Flight {
+    /// The reading that came closest to, or furthest past, its redline in
+    /// `alarms`, as the channel and `Alarms::percent_of_redline`.
+    pub fn peak_of_redline(&self, alarms: &Alarms) -> Option<(Channel, f64)> {
+        self.records
+            .iter()
+            .flat_map(|record| {
+                let egts = (1..=record.egt.len() as u8).map(Channel::Egt);
+                let chts = (1..=record.cht.len() as u8).map(Channel::Cht);
+                egts.chain(chts)
+                    .chain([Channel::OilTemp, Channel::Volts])
+                    .filter_map(|c| Some((c, alarms.percent_of_redline(record, c)?)))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// The alarm limits each record breaks, one entry per record, including
+    /// a CHT that cools faster than `max_cht_cool_rate` degrees a minute
+    /// since the record before.
+    pub fn alarm_violations(&self, alarms: &Alarms) -> Vec<Vec<AlarmKind>> {
+        let interval = self.recording_interval_secs().filter(|secs| *secs > 0);
+        let disconnected = disconnected_probes(&self.records);
+        let mut previous: Option<&Record> = None;
+        self.records
+            .iter()
+            .map(|record| {
+                let mut violations = alarms.violations(record, &disconnected);
+                let cooling = previous.zip(interval).zip(alarms.max_cht_cool_rate);
+                if let Some(((previous, interval), max)) = cooling {
+                    let too_fast = (1..=record.cht.len() as u8).map(Channel::Cht).any(|c| {
+                        match (previous.get(c), record.get(c)) {
+                            (Some(before), Some(now))
+                                if !previous.is_fault(c) && !record.is_fault(c) =>
+                            {
+                                (before - now) * 60.0 / f64::from(interval) > f64::from(max)
+                            }
+                            _ => false,
+                        }
+                    });
+                    if too_fast {
+                        violations.push(AlarmKind::MaxChtCoolRate);
+                        violations.sort();
+                    }
+                }
+                previous = Some(record);
+                violations
+            })
+            .collect()
+    }
+}
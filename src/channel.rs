@@ -0,0 +1,132 @@
+use std::ops::RangeInclusive;
+
+const LITRES_PER_GALLON: f64 = 3.785411784;
+const KPA_PER_INHG: f64 = 3.386389;
+const KPA_PER_PSI: f64 = 6.894757;
+
+/// Converts a temperature in degrees Fahrenheit to Celsius.
+pub fn fahrenheit_to_celsius(fahrenheit: f64) -> f64 {
+    (fahrenheit - 32.0) * 5.0 / 9.0
+}
+
+/// A decoded channel. Cylinder numbers start at 1.
+///
+/// Variants are declared in decode order, so sorting channels puts them in
+/// the order they appear in a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Channel {
+    Egt(u8),
+    Tit1,
+    Tit2,
+    Cht(u8),
+    OilTemp,
+    OilPressure,
+    Cdt,
+    Iat,
+    Volts,
+    Oat,
+    FuelUsed,
+    FuelFlow,
+    Map,
+    Rpm,
+}
+
+impl Channel {
+    /// Column label used in exports, e.g. `EGT1` or `FF`.
+    pub fn label(&self) -> String {
+        match self {
+            Channel::Egt(cylinder) => format!("EGT{cylinder}"),
+            Channel::Tit1 => "TIT1".to_string(),
+            Channel::Tit2 => "TIT2".to_string(),
+            Channel::Cht(cylinder) => format!("CHT{cylinder}"),
+            Channel::OilTemp => "OIL".to_string(),
+            Channel::OilPressure => "OILP".to_string(),
+            Channel::Cdt => "CDT".to_string(),
+            Channel::Iat => "IAT".to_string(),
+            Channel::Volts => "VOLTS".to_string(),
+            Channel::Oat => "OAT".to_string(),
+            Channel::FuelUsed => "USD".to_string(),
+            Channel::FuelFlow => "FF".to_string(),
+            Channel::Map => "MAP".to_string(),
+            Channel::Rpm => "RPM".to_string(),
+        }
+    }
+
+    /// The channel a `label` names, in any case.
+    pub fn from_label(label: &str) -> Option<Channel> {
+        let label = label.trim().to_ascii_uppercase();
+        let cylinder = |prefix: &str| {
+            label
+                .strip_prefix(prefix)?
+                .parse::<u8>()
+                .ok()
+                .filter(|n| *n > 0)
+        };
+        if let Some(n) = cylinder("EGT") {
+            return Some(Channel::Egt(n));
+        }
+        if let Some(n) = cylinder("CHT") {
+            return Some(Channel::Cht(n));
+        }
+        Some(match label.as_str() {
+            "TIT1" => Channel::Tit1,
+            "TIT2" => Channel::Tit2,
+            "OIL" => Channel::OilTemp,
+            "OILP" => Channel::OilPressure,
+            "CDT" => Channel::Cdt,
+            "IAT" => Channel::Iat,
+            "VOLTS" => Channel::Volts,
+            "OAT" => Channel::Oat,
+            "USD" => Channel::FuelUsed,
+            "FF" => Channel::FuelFlow,
+            "MAP" => Channel::Map,
+            "RPM" => Channel::Rpm,
+            _ => return None,
+        })
+    }
+
+    /// Readings outside this range come from a failed or open probe rather
+    /// than the engine. Only temperatures have one, in degrees Fahrenheit;
+    /// the limits are well past anything the instrument can display.
+    pub fn plausible_range(&self) -> Option<RangeInclusive<f64>> {
+        match self {
+            Channel::Egt(_) | Channel::Tit1 | Channel::Tit2 => Some(-100.0..=2000.0),
+            Channel::Cht(_) | Channel::Cdt | Channel::Iat => Some(-100.0..=1000.0),
+            Channel::OilTemp => Some(-100.0..=500.0),
+            Channel::Oat => Some(-100.0..=200.0),
+            _ => None,
+        }
+    }
+
+    /// Converts a reading to SI units: temperatures from Fahrenheit to
+    /// Celsius, fuel from US gallons to litres, manifold pressure from
+    /// inches of mercury and oil pressure from psi to kPa. Volts and RPM
+    /// are already SI and give `None`.
+    ///
+    /// The header's unit settings are not decoded, so readings are taken to
+    /// be in the instrument's default units.
+    pub fn to_si(&self, value: f64) -> Option<f64> {
+        match self {
+            Channel::Egt(_)
+            | Channel::Tit1
+            | Channel::Tit2
+            | Channel::Cht(_)
+            | Channel::OilTemp
+            | Channel::Cdt
+            | Channel::Iat
+            | Channel::Oat => Some(fahrenheit_to_celsius(value)),
+            Channel::FuelUsed | Channel::FuelFlow => Some(value * LITRES_PER_GALLON),
+            Channel::Map => Some(value * KPA_PER_INHG),
+            Channel::OilPressure => Some(value * KPA_PER_PSI),
+            Channel::Volts | Channel::Rpm => None,
+        }
+    }
+
+    /// Decimal places the channel is recorded with.
+    pub fn decimals(&self) -> usize {
+        match self {
+            Channel::Volts | Channel::FuelUsed | Channel::FuelFlow | Channel::Map => 1,
+            _ => 0,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use crate::channel::Channel;
+
+/// Options controlling how `EdmHeader::flights_with` and
+/// `EdmHeader::try_data_with` decode a file.
+///
+/// The defaults decode everything, exactly as `EdmHeader::flights` does.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DecodeConfig {
+    /// Fail with `ParseError::RecordLimit` rather than decode more than this
+    /// many records from one flight. A file from an untrusted source can
+    /// claim an enormous flight, and repeat counts let a few bytes stand for
+    /// hundreds of records.
+    pub max_records_per_flight: Option<usize>,
+    /// Fuel flow is stored in hundredths rather than tenths. The EDM-900
+    /// can be set to record it this way, but it cannot be detected: `$F`
+    /// holds only the flow units, tank levels and K-factors, no `$C` flag
+    /// bit is known to mean it, and the stored values are plausible either
+    /// way, since 12.34 gph read as tenths is a 123.4 gph flow that still
+    /// decodes. So it has to be set by the caller. `Flight::encode` always
+    /// writes tenths.
+    pub high_resolution_fuel_flow: bool,
+    /// Fail with `ParseError::UnknownTag` on a header line whose tag is not
+    /// known, rather than skip it, to find firmware that writes lines this
+    /// crate should be reading.
+    pub strict_tags: bool,
+    /// Fail with `ParseError::ConflictingLine` when a tag that can only
+    /// appear once is repeated with different contents. Otherwise the first
+    /// line wins, which is the best guess for a file salvaged from a bad
+    /// download.
+    pub reject_conflicting_lines: bool,
+    /// Fail with `ParseError::ProbeCountMismatch` when a flight records a
+    /// different number of EGT or CHT probes than `$C` configures. That is
+    /// always reported as `Diagnostic::SensorMismatch`, but a probe turned
+    /// off for one flight is legitimate, so it is only an error on request.
+    pub strict_probe_counts: bool,
+    /// Join flights that follow on within this many seconds into one, with
+    /// `merge_flights`, for a recording split by a power blip.
+    pub merge_within_secs: Option<i64>,
+    /// Two digit `$T` years below this are in the 2000s and the rest in the
+    /// 1900s. `None` uses `DecodeConfig::DEFAULT_YEAR_PIVOT`.
+    pub year_pivot: Option<u32>,
+}
+
+impl DecodeConfig {
+    /// How many decimal places a file decoded with these options stores
+    /// `channel` to: `Channel::decimals`, or one more for fuel flow in
+    /// hundredths.
+    pub fn decimals(&self, channel: Channel) -> usize {
+        match channel {
+            Channel::FuelFlow if self.high_resolution_fuel_flow => 2,
+            _ => channel.decimals(),
+        }
+    }
+
+    /// Year pivot used when `year_pivot` is not set, so `99` is 1999 and
+    /// `05` is 2005.
+    pub const DEFAULT_YEAR_PIVOT: u32 = 80;
+}
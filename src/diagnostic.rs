@@ -0,0 +1,74 @@
+use std::fmt;
+
+use crate::Sensors;
+
+/// Something unexpected that was worked around while decoding.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostic {
+    /// A flight's own flag word disagrees with the `$C` configuration. The
+    /// flight's flags were used to decode it.
+    SensorMismatch {
+        flight: i32,
+        config: Sensors,
+        recorded: Sensors,
+    },
+    /// A record failed its checksum or ran past the end of the flight, so
+    /// decoding of the flight stopped at `offset`.
+    BadRecord { flight: i32, offset: usize },
+    /// The file ends before a flight's block does, as it does when a
+    /// download is interrupted. The records that are there were decoded.
+    TruncatedFlight {
+        flight: i32,
+        expected_bytes: usize,
+        found_bytes: usize,
+    },
+    /// A flight's first record says the record before it repeats `count`
+    /// times, but there is no record before it. The count was ignored.
+    LeadingRepeat { flight: i32, count: u8 },
+    /// A header line's checksum did not match. The line was parsed anyway.
+    BadHeaderChecksum { line: String },
+    /// A header line has no `*` checksum. The whole line was parsed as data.
+    MissingHeaderChecksum { line: String },
+    /// A header line repeats a tag that can only appear once. The first
+    /// line with the tag was used and line `line` ignored.
+    DuplicateHeaderLine { tag: char, line: usize },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::SensorMismatch {
+                flight,
+                config,
+                recorded,
+            } => write!(
+                f,
+                "flight {flight}: recorded sensors {recorded:?} differ from $C config {config:?}"
+            ),
+            Diagnostic::BadRecord { flight, offset } => {
+                write!(f, "flight {flight}: bad record at byte {offset}")
+            }
+            Diagnostic::TruncatedFlight {
+                flight,
+                expected_bytes,
+                found_bytes,
+            } => write!(
+                f,
+                "flight {flight}: truncated to {found_bytes} of {expected_bytes} bytes"
+            ),
+            Diagnostic::LeadingRepeat { flight, count } => write!(
+                f,
+                "flight {flight}: first record repeats a record before it {count} times"
+            ),
+            Diagnostic::BadHeaderChecksum { line } => {
+                write!(f, "header line {line:?} failed its checksum")
+            }
+            Diagnostic::MissingHeaderChecksum { line } => {
+                write!(f, "header line {line:?} has no checksum")
+            }
+            Diagnostic::DuplicateHeaderLine { tag, line } => {
+                write!(f, "header line {line}: repeats ${tag}, the first was used")
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use crate::{
+    config::DecodeConfig,
+    diagnostic::Diagnostic,
+    flight::{
+        Flight, FlightHeader, FIELDS, INITIAL_VALUE, RPM, RPM_HIGH, SCALE_BITS, SCALE_FIELDS,
+    },
+    time::civil,
+    EdmHeader, ParseError,
+};
+
+/// Result of re-encoding a decoded flight and comparing it with the file.
+#[derive(Debug, Default)]
+pub struct SelfTest {
+    pub flight: i32,
+    pub records: usize,
+    /// Decoding the re-encoded records gives back the same values.
+    pub values_match: bool,
+    /// The re-encoded records are byte for byte the ones in the file. The
+    /// EDM-900 also records fields this crate does not decode, and sends
+    /// fields that did not change, and the encoder can do neither, so this
+    /// is false for real downloads even when `values_match` holds.
+    pub bytes_match: bool,
+    /// Offset into the flight's records of the first byte that differs.
+    pub first_difference: Option<usize>,
+}
+
+impl FlightHeader {
+    /// Encodes the header, leaving the words we do not decode zeroed.
+    pub fn encode(&self, long: bool) -> Vec<u8> {
+        let words = if long {
+            FlightHeader::LONG_WORDS
+        } else {
+            FlightHeader::WORDS
+        };
+        let (date, time) = match self.start_time {
+            Some(start) => {
+                let (year, month, day, hour, minute, second) = civil(start);
+                (
+                    ((year - 2000) as u16) << 9 | (month as u16) << 5 | day as u16,
+                    (hour as u16) << 11 | (minute as u16) << 5 | (second / 2) as u16,
+                )
+            }
+            None => (0, 0),
+        };
+
+        let mut header = vec![0u16; words];
+        header[0] = self.number as u16;
+        header[1] = self.flags as u16;
+        header[2] = (self.flags >> 16) as u16;
+        header[words - 3] = self.interval_secs as u16;
+        header[words - 2] = date;
+        header[words - 1] = time;
+
+        let mut bytes = header
+            .iter()
+            .flat_map(|word| word.to_be_bytes())
+            .collect::<Vec<u8>>();
+        bytes.push(negated_sum(&bytes));
+        bytes
+    }
+}
+
+impl Flight {
+    /// Encodes the flight back into the block layout `EdmHeader::flights`
+    /// reads, or `None` if it has no header or a value changes too much
+    /// between records to be stored.
+    pub fn encode(&self, long_header: bool) -> Option<Vec<u8>> {
+        let mut block = self.header.as_ref()?.encode(long_header);
+        block.extend(self.encode_records()?);
+        Some(block)
+    }
+
+    /// Encodes just the records, one per sample, without using repeat counts.
+    pub(crate) fn encode_records(&self) -> Option<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let mut previous = [INITIAL_VALUE; FIELDS];
+        for record in &self.records {
+            let values = record.to_values(&previous);
+            let mut deltas = [0i32; FIELDS];
+            for (field, delta) in deltas.iter_mut().enumerate() {
+                *delta = values[field] - previous[field];
+            }
+            bytes.extend(encode_record(&deltas)?);
+            previous = values;
+        }
+        Some(bytes)
+    }
+}
+
+/// Encodes one record from the change in every field since the last one.
+fn encode_record(deltas: &[i32; FIELDS]) -> Option<Vec<u8>> {
+    let mut field_flags = [0u8; FIELDS / 8];
+    let mut signs = [0u8; FIELDS / 8];
+    let mut low = [0u8; FIELDS];
+    let mut scale_flags = [0u8; SCALE_BITS.len()];
+    let mut high = [[0u8; 8]; SCALE_BITS.len()];
+    let mut rpm_high = None;
+
+    for (field, delta) in deltas.iter().enumerate() {
+        if *delta == 0 {
+            continue;
+        }
+        let magnitude = delta.unsigned_abs();
+        let (byte, bit) = (field / 8, field % 8);
+        field_flags[byte] |= 1 << bit;
+        if *delta < 0 {
+            signs[byte] |= 1 << bit;
+        }
+        low[field] = magnitude as u8;
+
+        let scale = SCALE_FIELDS
+            .iter()
+            .position(|first| (*first..*first + 8).contains(&field));
+        match (magnitude >> 8, scale) {
+            (0, _) => (),
+            (carry, Some(scale)) if carry <= 0xff => {
+                scale_flags[scale] |= 1 << (field - SCALE_FIELDS[scale]);
+                high[scale][field - SCALE_FIELDS[scale]] = carry as u8;
+            }
+            (carry, None) if field == RPM && carry <= 0xff => rpm_high = Some(carry as u8),
+            _ => return None,
+        }
+    }
+    if let Some(carry) = rpm_high {
+        field_flags[RPM_HIGH / 8] |= 1 << (RPM_HIGH % 8);
+        low[RPM_HIGH] = carry;
+    }
+
+    let mut decode_flags = 0u16;
+    for bit in 0..16u16 {
+        let present = match SCALE_BITS.iter().position(|scale| *scale == bit) {
+            Some(scale) => scale_flags[scale] != 0,
+            None => field_flags[Flight::field_byte(bit)] != 0,
+        };
+        if present {
+            decode_flags |= 1 << bit;
+        }
+    }
+    // Like the EDM, announce an empty field flag byte for a record with no
+    // changes, so it cannot be mistaken for padding
+    if decode_flags == 0 {
+        decode_flags = 1;
+    }
+    let present = (0..16u16)
+        .filter(|bit| decode_flags >> bit & 1 == 1)
+        .collect::<Vec<u16>>();
+
+    let mut record = Vec::new();
+    record.extend(decode_flags.to_be_bytes());
+    record.extend(decode_flags.to_be_bytes());
+    record.push(0);
+    for bit in &present {
+        record.push(match SCALE_BITS.iter().position(|scale| scale == bit) {
+            Some(scale) => scale_flags[scale],
+            None => field_flags[Flight::field_byte(*bit)],
+        });
+    }
+    for bit in present.iter().filter(|bit| !SCALE_BITS.contains(bit)) {
+        record.push(signs[Flight::field_byte(*bit)]);
+    }
+    for bit in &present {
+        match SCALE_BITS.iter().position(|scale| scale == bit) {
+            Some(scale) => record.extend(
+                (0..8)
+                    .filter(|i| scale_flags[scale] >> i & 1 == 1)
+                    .map(|i| high[scale][i]),
+            ),
+            None => {
+                let byte = Flight::field_byte(*bit);
+                record.extend(
+                    (0..8)
+                        .filter(|i| field_flags[byte] >> i & 1 == 1)
+                        .map(|i| low[byte * 8 + i]),
+                )
+            }
+        }
+    }
+    record.push(negated_sum(&record));
+    Some(record)
+}
+
+/// The checksum used by flight headers and records.
+fn negated_sum(bytes: &[u8]) -> u8 {
+    bytes
+        .iter()
+        .fold(0u8, |sum, x| sum.wrapping_add(*x))
+        .wrapping_neg()
+}
+
+impl EdmHeader {
+    /// Decodes a flight, re-encodes its records and compares the result
+    /// with the file. Returns `None` if the flight is not in the file, and
+    /// fails with `ParseError::SelfTestFlight` if it is there but did not
+    /// decode cleanly or cannot be re-encoded.
+    pub fn self_test(&self, number: i32) -> Result<Option<SelfTest>, ParseError> {
+        let long_header = self
+            .try_data()?
+            .features
+            .is_some_and(|f| f.long_flight_header());
+        let Some(flight) = self.flights()?.into_iter().find(|f| f.number == number) else {
+            return Ok(None);
+        };
+        let clean = !flight.diagnostics.iter().any(|d| {
+            matches!(
+                d,
+                Diagnostic::BadRecord { .. } | Diagnostic::TruncatedFlight { .. }
+            )
+        });
+        clean
+            .then(|| self.compare_flight(&flight, long_header))
+            .flatten()
+            .map(Some)
+            .ok_or(ParseError::SelfTestFlight { flight: number })
+    }
+
+    fn compare_flight(&self, flight: &Flight, long_header: bool) -> Option<SelfTest> {
+        let header_len = flight.header.as_ref()?.encode(long_header).len();
+        let end = (flight.offset + flight.words * 2).min(self.len());
+        let original = self.file_stream.get(flight.offset + header_len..end)?;
+        let encoded = flight.encode_records()?;
+
+        // Decode the re-encoded flight the same way the file was decoded
+        let mut block = flight.header.as_ref()?.encode(long_header);
+        block.extend(&encoded);
+        let mut decoded = Flight {
+            number: flight.number,
+            words: block.len().div_ceil(2),
+            ..Default::default()
+        };
+        decoded
+            .decode(&block, None, long_header, &DecodeConfig::default())
+            .ok()?;
+        let values_match = decoded.records.len() == flight.records.len()
+            && decoded.records.iter().zip(&flight.records).all(|(a, b)| {
+                a.to_values(&[INITIAL_VALUE; FIELDS]) == b.to_values(&[INITIAL_VALUE; FIELDS])
+            });
+
+        let first_difference = encoded
+            .iter()
+            .zip(original)
+            .position(|(a, b)| a != b)
+            .or_else(|| {
+                // `$D` rounds an odd block up, taking in the next flight's
+                // first byte
+                (encoded.len() != original.len() && encoded.len() + 1 != original.len())
+                    .then_some(encoded.len().min(original.len()))
+            });
+        Some(SelfTest {
+            flight: flight.number,
+            records: flight.records.len(),
+            values_match,
+            bytes_match: first_difference.is_none(),
+            first_difference,
+        })
+    }
+}
@@ -0,0 +1,216 @@
+use std::{error, fmt};
+
+use crate::channel::Channel;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The header has no `$C` line, so there is no way to know which
+    /// channels the flight records hold.
+    MissingConfig,
+    /// No line ending in the file is followed by binary data, so the header
+    /// never ends.
+    HeaderEnd,
+    /// The header is not ASCII text, outside of the registration.
+    HeaderEncoding,
+    /// A flight holds more records than `DecodeConfig::max_records_per_flight`
+    /// allows.
+    RecordLimit { flight: i32, limit: usize },
+    /// Header line `line` (counting from 1) has fewer fields than its tag
+    /// needs.
+    FieldCount {
+        tag: char,
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// Header line `line` (counting from 1) has a tag this crate does not
+    /// know, and `DecodeConfig::strict_tags` is set.
+    UnknownTag { tag: char, line: usize },
+    /// Header line `line` (counting from 1) repeats a tag that can only
+    /// appear once with different contents, and
+    /// `DecodeConfig::reject_conflicting_lines` is set.
+    ConflictingLine { tag: char, line: usize },
+    /// A flight's flag word records a different number of EGT or CHT probes
+    /// than `$C`, and `DecodeConfig::strict_probe_counts` is set. Each count
+    /// is given as the `$C` count then the flight's.
+    ProbeCountMismatch {
+        flight: i32,
+        egt: (i32, i32),
+        cht: (i32, i32),
+    },
+    /// `EdmHeader::self_test` was asked for a flight that did not decode
+    /// cleanly, or that cannot be re-encoded, so there is nothing to
+    /// compare.
+    SelfTestFlight { flight: i32 },
+    /// Field `field` (counting from 1) of header line `line` is not valid.
+    InvalidField {
+        tag: char,
+        line: usize,
+        field: usize,
+        value: String,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingConfig => {
+                write!(f, "no $C config line, flights cannot be decoded safely")
+            }
+            ParseError::HeaderEnd => write!(f, "could not find the end of the header"),
+            ParseError::HeaderEncoding => write!(f, "the header is not ASCII text"),
+            ParseError::RecordLimit { flight, limit } => {
+                write!(f, "flight {flight} has more than {limit} records")
+            }
+            ParseError::FieldCount {
+                tag,
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "header line {line} (${tag}): expected {expected} fields, found {found}"
+            ),
+            ParseError::UnknownTag { tag, line } => {
+                write!(f, "header line {line}: unknown tag ${tag}")
+            }
+            ParseError::ConflictingLine { tag, line } => {
+                write!(f, "header line {line}: conflicting second ${tag} line")
+            }
+            ParseError::SelfTestFlight { flight } => {
+                write!(
+                    f,
+                    "flight {flight} did not decode cleanly enough to re-encode"
+                )
+            }
+            ParseError::ProbeCountMismatch { flight, egt, cht } => write!(
+                f,
+                "flight {flight}: records {} EGT and {} CHT probes, $C has {} and {}",
+                egt.1, cht.1, egt.0, cht.0
+            ),
+            ParseError::InvalidField {
+                tag,
+                line,
+                field,
+                value,
+            } => write!(
+                f,
+                "header line {line} (${tag}): field {field} is not valid: {value:?}"
+            ),
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportError {
+    /// A requested column is not one of the flight's `field_labels`, nor
+    /// `EGT` or `CHT` for every cylinder.
+    UnknownColumn {
+        name: String,
+        available: Vec<String>,
+    },
+    /// A channel alias is already the name of another column.
+    AliasCollision { channel: Channel, alias: String },
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::UnknownColumn { name, available } => write!(
+                f,
+                "unknown column {name:?}, expected one of {}",
+                available.join(", ")
+            ),
+            ExportError::AliasCollision { channel, alias } => write!(
+                f,
+                "alias {alias:?} for {} is already the name of another column",
+                channel.label()
+            ),
+        }
+    }
+}
+
+impl error::Error for ExportError {}
+
+/// Why `Flight::from_csv` could not read a CSV back. Returned inside an
+/// `io::Error` of kind `InvalidData`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportError {
+    /// A label given to read is not a `Channel::label`.
+    UnknownLabel { label: String },
+    /// The header row is not `TIME`, the labels given, then `MARK`.
+    HeaderMismatch {
+        expected: Vec<String>,
+        found: Vec<String>,
+    },
+    /// Row `row` (counting from 1 after the header) has the wrong number of
+    /// cells.
+    CellCount {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A cell of row `row` (counting from 1 after the header) cannot be
+    /// read for its column.
+    InvalidCell {
+        row: usize,
+        column: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::UnknownLabel { label } => write!(f, "unknown channel {label:?}"),
+            ImportError::HeaderMismatch { expected, found } => write!(
+                f,
+                "expected the columns {}, found {}",
+                expected.join(","),
+                found.join(",")
+            ),
+            ImportError::CellCount {
+                row,
+                expected,
+                found,
+            } => write!(f, "row {row}: expected {expected} cells, found {found}"),
+            ImportError::InvalidCell { row, column, value } => {
+                write!(f, "row {row}: {value:?} is not valid for {column}")
+            }
+        }
+    }
+}
+
+impl error::Error for ImportError {}
+
+/// Why an analysis setting such as `Alarms::set_redline` was refused.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnalysisError {
+    /// A redline given by the user is not a positive number.
+    InvalidRedline { name: String, value: f64 },
+    /// A redline was given for a channel `Alarms::set_redline` does not
+    /// know.
+    UnknownRedline {
+        name: String,
+        available: Vec<String>,
+    },
+}
+
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalysisError::InvalidRedline { name, value } => {
+                write!(f, "redline {value} for {name} is not a positive number")
+            }
+            AnalysisError::UnknownRedline { name, available } => write!(
+                f,
+                "unknown redline {name:?}, expected one of {}",
+                available.join(", ")
+            ),
+        }
+    }
+}
+
+impl error::Error for AnalysisError {}
@@ -0,0 +1,588 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    io::{self, BufRead, BufReader, Read, Write},
+};
+
+use crate::{
+    analysis::{disconnected_probes, downsample, egt_spread, fill_gaps},
+    channel::Channel,
+    config::DecodeConfig,
+    error::{ExportError, ImportError},
+    flight::{Flight, Record},
+    time::{civil, format_timestamp, parse_timestamp},
+};
+
+/// Column layout used when writing a flight as CSV.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CsvDialect {
+    /// A `TIME` column, one column per recorded channel named by
+    /// `Channel::label`, then `MARK` as 1 or 0.
+    #[default]
+    Native,
+    /// The column names and order of a JPI EzTrends export, so existing
+    /// spreadsheets and scripts built around it keep working.
+    EzTrends,
+}
+
+/// How `Flight::write_csv_with` lays out a flight.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportOptions {
+    pub dialect: CsvDialect,
+    /// Only export these channels, as picked by `Sensors::select_channels`.
+    /// The time columns are always written; derived columns such as `MARK`
+    /// and `DIF` are left out when a selection is given.
+    pub channels: Option<Vec<Channel>>,
+    /// Merge records into buckets of this many seconds with `downsample`.
+    pub downsample_secs: Option<i64>,
+    /// Write the native `TIME` column as seconds since the first record, to
+    /// line flights up on one axis. EzTrends keeps its `DATE` and `TIME`.
+    pub elapsed_time: bool,
+    /// Write readings in SI units, converted by `Channel::to_si`.
+    pub si_units: bool,
+    /// Write readings as the integers the instrument stored, from
+    /// `Record::raw`, for working out scales. Takes precedence over
+    /// `si_units`.
+    pub raw_values: bool,
+    /// The options the flights were decoded with, for the precision values
+    /// are written to and the scale `raw_values` undoes.
+    pub decode: DecodeConfig,
+    /// Write a run of records that read the same, once scaled and
+    /// formatted, as their first record with a `COUNT` column saying how
+    /// many there were. Time columns are not compared.
+    pub dedupe: bool,
+    /// Lay records onto a regular grid at the recording interval, or the
+    /// `downsample_secs` bucket, with `fill_gaps`.
+    pub fill_gaps: bool,
+    /// Column names to use instead of a channel's usual one, to match an
+    /// existing spreadsheet. Checked with `check_aliases` before writing.
+    pub aliases: HashMap<Channel, String>,
+}
+
+/// A flight laid out one column per channel, for analytics and columnar
+/// formats. Every column has one entry per record.
+///
+/// The crate has no dependencies, so there is no Arrow `RecordBatch`
+/// export. The columns map onto one directly: `timestamps` as a nullable
+/// Int64, each channel as a nullable Float64 named by `Channel::label`,
+/// which in decode order is `Sensors::field_labels`, and `marks` as Int32.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FlightColumns {
+    pub timestamps: Vec<Option<i64>>,
+    /// Readings scaled as `Record::get` gives them, `None` where a record
+    /// has no value.
+    pub channels: BTreeMap<Channel, Vec<Option<f64>>>,
+    /// Raw `Record::mark` of every record.
+    pub marks: Vec<i32>,
+}
+
+/// EzTrends columns after `INDEX`, `DATE` and `TIME`. Each entry is the
+/// column name and the channel it holds; `DIF` is derived from the EGTs.
+const EZTRENDS_COLUMNS: [(&str, Option<Channel>); 11] = [
+    ("T1", Some(Channel::Tit1)),
+    ("T2", Some(Channel::Tit2)),
+    ("OAT", Some(Channel::Oat)),
+    ("DIF", None),
+    ("CDT", Some(Channel::Cdt)),
+    ("IAT", Some(Channel::Iat)),
+    ("MAP", Some(Channel::Map)),
+    ("RPM", Some(Channel::Rpm)),
+    ("FF", Some(Channel::FuelFlow)),
+    ("OILP", Some(Channel::OilPressure)),
+    ("BAT", Some(Channel::Volts)),
+];
+
+/// EzTrends columns that come after `BAT`.
+const EZTRENDS_TRAILING: [(&str, Channel); 2] =
+    [("OILT", Channel::OilTemp), ("USD", Channel::FuelUsed)];
+
+/// What one CSV column holds.
+#[derive(Debug, Clone, Copy)]
+enum Column {
+    Index,
+    Date,
+    Time,
+    Timestamp,
+    Elapsed(i64),
+    Spread,
+    Mark,
+    Count,
+    Flight(i32),
+    /// A column of `write_flights_csv` this flight did not record.
+    Empty,
+    Value(Channel),
+}
+
+/// Writes all of `flights` as one CSV, for loading into a database. The
+/// first column, `FLIGHT`, holds the flight number, followed by the
+/// columns `Flight::write_csv_with` would write. A channel only some
+/// flights recorded is empty in the others.
+pub fn write_flights_csv<W: Write>(
+    flights: &[Flight],
+    mut writer: W,
+    options: &ExportOptions,
+) -> io::Result<()> {
+    check_aliases(&options.aliases).map_err(invalid_input)?;
+    let layouts = flights
+        .iter()
+        .map(|flight| flight.csv_columns(options))
+        .collect::<Vec<Vec<(String, Column)>>>();
+    // Every flight's columns, each one new to the list placed after the
+    // column it follows in its own flight
+    let mut names = vec!["FLIGHT".to_string()];
+    for layout in &layouts {
+        for (i, (name, _)) in layout.iter().enumerate() {
+            if names.contains(name) {
+                continue;
+            }
+            let after = match i {
+                0 => 0,
+                _ => names
+                    .iter()
+                    .position(|n| *n == layout[i - 1].0)
+                    .unwrap_or(0),
+            };
+            names.insert(after + 1, name.clone());
+        }
+    }
+    let header = names.iter().map(|name| (name.clone(), Column::Empty));
+    write_header(&mut writer, &header.collect::<Vec<(String, Column)>>())?;
+    for (flight, layout) in flights.iter().zip(&layouts) {
+        let columns = names
+            .iter()
+            .map(|name| {
+                let column = match name.as_str() {
+                    "FLIGHT" => Column::Flight(flight.number),
+                    _ => layout
+                        .iter()
+                        .find(|(n, _)| n == name)
+                        .map_or(Column::Empty, |(_, column)| *column),
+                };
+                (name.clone(), column)
+            })
+            .collect::<Vec<(String, Column)>>();
+        flight.write_rows(&mut writer, &columns, options)?;
+    }
+    Ok(())
+}
+
+/// Checks that no two channels share an alias, and that no alias is the
+/// name of a column it would be confused with: another channel's label or
+/// one of the time and derived columns.
+pub fn check_aliases(aliases: &HashMap<Channel, String>) -> Result<(), ExportError> {
+    const RESERVED: [&str; 7] = ["TIME", "MARK", "COUNT", "FLIGHT", "INDEX", "DATE", "DIF"];
+    // Checked in channel order so the error does not depend on hashing
+    let mut sorted = aliases.iter().collect::<Vec<(&Channel, &String)>>();
+    sorted.sort();
+    for (i, (channel, alias)) in sorted.iter().enumerate() {
+        let shared = sorted[..i]
+            .iter()
+            .any(|(_, a)| a.eq_ignore_ascii_case(alias));
+        let relabels =
+            Channel::from_label(alias).is_some_and(|c| c != **channel && !aliases.contains_key(&c));
+        if shared || relabels || RESERVED.iter().any(|r| r.eq_ignore_ascii_case(alias)) {
+            return Err(ExportError::AliasCollision {
+                channel: **channel,
+                alias: (*alias).clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn invalid_input(error: ExportError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, error)
+}
+
+fn write_header<W: Write>(mut writer: W, columns: &[(String, Column)]) -> io::Result<()> {
+    writeln!(
+        writer,
+        "{}",
+        columns
+            .iter()
+            .map(|(name, _)| csv_escape(name))
+            .collect::<Vec<Cow<str>>>()
+            .join(",")
+    )
+}
+
+impl Flight {
+    /// Writes the flight's records as CSV, one row per record.
+    pub fn write_csv<W: Write>(&self, writer: W, dialect: CsvDialect) -> io::Result<()> {
+        self.write_csv_with(
+            writer,
+            &ExportOptions {
+                dialect,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Writes the flight's records as CSV, laid out by `options`.
+    pub fn write_csv_with<W: Write>(
+        &self,
+        mut writer: W,
+        options: &ExportOptions,
+    ) -> io::Result<()> {
+        check_aliases(&options.aliases).map_err(invalid_input)?;
+        let columns = self.csv_columns(options);
+        write_header(&mut writer, &columns)?;
+        self.write_rows(&mut writer, &columns, options)
+    }
+
+    /// Reads records back from a CSV written by `write_csv` in the native
+    /// dialect, whose columns are `TIME`, one per channel in `labels`, then
+    /// `MARK`. Only what the CSV holds comes back: the flight has no number
+    /// or header, and a marked record gets the pilot mark 2 while every
+    /// other mark reads as 0.
+    pub fn from_csv<R: Read, S: AsRef<str>>(reader: R, labels: &[S]) -> io::Result<Flight> {
+        let invalid = |error: ImportError| io::Error::new(io::ErrorKind::InvalidData, error);
+        let channels = labels
+            .iter()
+            .map(|label| {
+                Channel::from_label(label.as_ref()).ok_or_else(|| {
+                    invalid(ImportError::UnknownLabel {
+                        label: label.as_ref().to_string(),
+                    })
+                })
+            })
+            .collect::<io::Result<Vec<Channel>>>()?;
+        let expected = std::iter::once("TIME".to_string())
+            .chain(labels.iter().map(|label| label.as_ref().to_string()))
+            .chain(std::iter::once("MARK".to_string()))
+            .collect::<Vec<String>>();
+
+        let mut lines = BufReader::new(reader).lines();
+        let found = csv_split(
+            lines
+                .next()
+                .transpose()?
+                .unwrap_or_default()
+                .trim_end_matches('\r'),
+        );
+        if found.len() != expected.len()
+            || found
+                .iter()
+                .zip(&expected)
+                .any(|(a, b)| !a.eq_ignore_ascii_case(b))
+        {
+            return Err(invalid(ImportError::HeaderMismatch { expected, found }));
+        }
+
+        let mut records = Vec::new();
+        for (index, line) in lines.enumerate() {
+            let line = line?;
+            // A spreadsheet may have saved the file with CRLF line endings
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                continue;
+            }
+            let row = index + 1;
+            let cells = csv_split(line);
+            if cells.len() != expected.len() {
+                return Err(invalid(ImportError::CellCount {
+                    row,
+                    expected: expected.len(),
+                    found: cells.len(),
+                }));
+            }
+            let bad_cell = |column: &str, value: &str| {
+                invalid(ImportError::InvalidCell {
+                    row,
+                    column: column.to_string(),
+                    value: value.to_string(),
+                })
+            };
+            let mut record = Record::default();
+            if !cells[0].is_empty() {
+                record.timestamp =
+                    Some(parse_timestamp(&cells[0]).ok_or_else(|| bad_cell("TIME", &cells[0]))?);
+            }
+            for ((channel, label), cell) in channels.iter().zip(&expected[1..]).zip(&cells[1..]) {
+                if !cell.is_empty() {
+                    let value = cell.parse::<f64>().map_err(|_| bad_cell(label, cell))?;
+                    record.set(*channel, value);
+                }
+            }
+            record.mark = match cells[cells.len() - 1].as_str() {
+                "0" => 0,
+                "1" => 2,
+                mark => return Err(bad_cell("MARK", mark)),
+            };
+            records.push(record);
+        }
+        Ok(Flight {
+            records,
+            ..Default::default()
+        })
+    }
+
+    /// Writes one CSV row per record, without the header row.
+    fn write_rows<W: Write>(
+        &self,
+        mut writer: W,
+        columns: &[(String, Column)],
+        options: &ExportOptions,
+    ) -> io::Result<()> {
+        let mut records = match options.downsample_secs {
+            Some(secs) => downsample(&self.records, secs),
+            None => self.records.clone(),
+        };
+        let grid = options
+            .downsample_secs
+            .or(self.recording_interval_secs().map(i64::from));
+        if let Some(secs) = grid.filter(|_| options.fill_gaps) {
+            records = fill_gaps(&records, secs);
+        }
+        let mut write_row = |row: Vec<String>, count: usize| {
+            let row = columns
+                .iter()
+                .zip(row)
+                .map(|((_, column), cell)| match column {
+                    Column::Count => count.to_string(),
+                    _ => csv_escape(&cell).into_owned(),
+                })
+                .collect::<Vec<String>>();
+            writeln!(writer, "{}", row.join(","))
+        };
+        let disconnected = disconnected_probes(&self.records);
+        // Readings and marks are written by `Record::to_row_with`
+        let labels = columns
+            .iter()
+            .map(|(_, column)| match column {
+                Column::Value(channel) => channel.label(),
+                Column::Mark => "MARK".to_string(),
+                _ => String::new(),
+            })
+            .collect::<Vec<String>>();
+        let labels = labels.iter().map(String::as_str).collect::<Vec<&str>>();
+        let mut pending: Option<(Vec<String>, usize)> = None;
+        for (index, record) in records.iter().enumerate() {
+            let row = columns
+                .iter()
+                .zip(record.to_row_with(&labels, options))
+                .map(|((_, column), value)| match column {
+                    Column::Value(_) | Column::Mark => value,
+                    _ => column.format(index, record, &disconnected, options),
+                })
+                .collect::<Vec<String>>();
+            if let Some((first, count)) = &mut pending {
+                let same = columns
+                    .iter()
+                    .zip(first.iter().zip(&row))
+                    .all(|((_, column), (a, b))| column.is_time() || a == b);
+                if options.dedupe && same {
+                    *count += 1;
+                    continue;
+                }
+            }
+            if let Some((first, count)) = pending.replace((row, 1)) {
+                write_row(first, count)?;
+            }
+        }
+        if let Some((first, count)) = pending {
+            write_row(first, count)?;
+        }
+        Ok(())
+    }
+
+    /// The CSV header row `write_csv` would write.
+    pub fn csv_header(&self, options: &ExportOptions) -> Vec<String> {
+        self.csv_columns(options)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// The flight's records as one column per channel. The channels are
+    /// those the flight header says were recorded, or every channel a
+    /// record holds if there is no header.
+    pub fn to_columns(&self) -> FlightColumns {
+        let channels = match &self.header {
+            Some(header) => header.sensors.present(),
+            None => {
+                let mut channels = self
+                    .records
+                    .iter()
+                    .flat_map(Record::channels)
+                    .collect::<Vec<Channel>>();
+                channels.sort();
+                channels.dedup();
+                channels
+            }
+        };
+        FlightColumns {
+            timestamps: self.records.iter().map(|r| r.timestamp).collect(),
+            channels: channels
+                .into_iter()
+                .map(|channel| {
+                    let values = self.records.iter().map(|r| r.get(channel)).collect();
+                    (channel, values)
+                })
+                .collect(),
+            marks: self.records.iter().map(|r| r.mark).collect(),
+        }
+    }
+
+    fn csv_columns(&self, options: &ExportOptions) -> Vec<(String, Column)> {
+        let mut present = self
+            .header
+            .as_ref()
+            .map(|h| h.sensors.present())
+            .unwrap_or_default();
+        let derived = options.channels.is_none();
+        if let Some(channels) = &options.channels {
+            present.retain(|c| channels.contains(c));
+        }
+        let mut columns = match options.dialect {
+            CsvDialect::Native => {
+                let start = self.records.iter().find_map(|r| r.timestamp);
+                let time = match start {
+                    Some(start) if options.elapsed_time => Column::Elapsed(start),
+                    _ => Column::Timestamp,
+                };
+                std::iter::once(("TIME".to_string(), time))
+                    .chain(present.iter().map(|c| (c.label(), Column::Value(*c))))
+                    .chain(derived.then(|| ("MARK".to_string(), Column::Mark)))
+                    .collect()
+            }
+            CsvDialect::EzTrends => {
+                let mut columns = vec![
+                    ("INDEX".to_string(), Column::Index),
+                    ("DATE".to_string(), Column::Date),
+                    ("TIME".to_string(), Column::Time),
+                ];
+                for channel in &present {
+                    if let Channel::Egt(n) = channel {
+                        columns.push((format!("E{n}"), Column::Value(*channel)));
+                    }
+                }
+                for channel in &present {
+                    if let Channel::Cht(n) = channel {
+                        columns.push((format!("C{n}"), Column::Value(*channel)));
+                    }
+                }
+                let has_egt = derived && present.iter().any(|c| matches!(c, Channel::Egt(_)));
+                for (name, channel) in EZTRENDS_COLUMNS {
+                    match channel {
+                        Some(channel) if present.contains(&channel) => {
+                            columns.push((name.to_string(), Column::Value(channel)))
+                        }
+                        None if has_egt => columns.push((name.to_string(), Column::Spread)),
+                        _ => (),
+                    }
+                }
+                for (name, channel) in EZTRENDS_TRAILING {
+                    if present.contains(&channel) {
+                        columns.push((name.to_string(), Column::Value(channel)));
+                    }
+                }
+                if derived {
+                    columns.push(("MARK".to_string(), Column::Mark));
+                }
+                columns
+            }
+        };
+        for (name, column) in &mut columns {
+            if let Column::Value(channel) = column {
+                if let Some(alias) = options.aliases.get(channel) {
+                    name.clone_from(alias);
+                }
+            }
+        }
+        if options.dedupe {
+            columns.push(("COUNT".to_string(), Column::Count));
+        }
+        columns
+    }
+}
+
+/// Quotes a CSV field as RFC 4180 asks, if it holds a comma, a quote or a
+/// line break, doubling any quotes inside it.
+pub fn csv_escape(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Splits one CSV row into its fields, undoing `csv_escape`.
+fn csv_split(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().expect("always one field");
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => field.push(c),
+        }
+    }
+    fields
+}
+
+impl Column {
+    /// Whether the column says when a record was taken rather than what it
+    /// read.
+    fn is_time(&self) -> bool {
+        matches!(
+            self,
+            Column::Index
+                | Column::Date
+                | Column::Time
+                | Column::Timestamp
+                | Column::Elapsed(_)
+                | Column::Flight(_)
+        )
+    }
+
+    /// The cell for `record`. The EGT spread leaves out the `disconnected`
+    /// probes of its flight.
+    fn format(
+        &self,
+        index: usize,
+        record: &Record,
+        disconnected: &[Channel],
+        options: &ExportOptions,
+    ) -> String {
+        match self {
+            Column::Index => index.to_string(),
+            Column::Date => record
+                .timestamp
+                .map(|ts| {
+                    let (year, month, day, ..) = civil(ts);
+                    format!("{month:02}/{day:02}/{year}")
+                })
+                .unwrap_or_default(),
+            Column::Time => record
+                .timestamp
+                .map(|ts| {
+                    let (.., hour, minute, second) = civil(ts);
+                    format!("{hour:02}:{minute:02}:{second:02}")
+                })
+                .unwrap_or_default(),
+            Column::Timestamp => record.timestamp.map(format_timestamp).unwrap_or_default(),
+            Column::Elapsed(start) => record
+                .timestamp
+                .map(|ts| (ts - start).to_string())
+                .unwrap_or_default(),
+            Column::Spread => egt_spread(record, disconnected)
+                .map(|spread| match options.si_units && !options.raw_values {
+                    // A difference, so only the scale changes
+                    true => format!("{:.1}", f64::from(spread) * 5.0 / 9.0),
+                    false => spread.to_string(),
+                })
+                .unwrap_or_default(),
+            Column::Flight(number) => number.to_string(),
+            // Filled in when the run of records is written, or by
+            // `Record::to_row_with`
+            Column::Count | Column::Empty | Column::Mark | Column::Value(_) => String::new(),
+        }
+    }
+}
@@ -0,0 +1,723 @@
+use std::time::Duration;
+
+use crate::{
+    channel::Channel, config::DecodeConfig, diagnostic::Diagnostic, error::ParseError,
+    export::ExportOptions, time::epoch_seconds, Sensors,
+};
+
+/// One `$D` entry: a recorded flight and the size of its binary block.
+///
+/// Entries from the header index only carry `number` and `words`; the rest
+/// is filled in by `EdmHeader::flights`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Flight {
+    pub number: i32,
+    /// Length of the flight's data block in 16-bit words.
+    pub words: usize,
+    /// Byte offset of the flight's data block in the file.
+    pub offset: usize,
+    pub header: Option<FlightHeader>,
+    pub records: Vec<Record>,
+    /// Number of records, when counted by `EdmHeader::skim_flights`
+    /// without decoding them.
+    pub samples: Option<usize>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// The binary header at the start of every flight block.
+///
+/// There is no separate layout for coarser recording settings. A flight
+/// recorded with fewer channels or a longer interval says so here, and each
+/// record flags the fields it carries, so every flight decodes the same way.
+///
+/// The EDM-900 words between the flags and the interval are not decoded.
+/// The first three repeat values from `$C` and the rest look like a GPS
+/// position; none of them moves between flights the way an engine hours
+/// count would, and no `$` line holds one either.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FlightHeader {
+    pub number: i32,
+    pub flags: u32,
+    /// Sensors recorded in this flight, which may differ from `$C`.
+    pub sensors: Sensors,
+    pub interval_secs: i32,
+    /// Start of the flight in seconds since the Unix epoch. An instrument
+    /// whose clock was never set writes a zero date, which gives `None`
+    /// rather than a made up date, and its records have no timestamps.
+    pub start_time: Option<i64>,
+}
+
+/// One sample of every recorded channel.
+///
+/// Temperatures are whole degrees in the instrument's unit, normally
+/// Fahrenheit. A channel the flight did not record is `None`, or an empty
+/// list for the cylinder probes.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Record {
+    /// Seconds since the Unix epoch, if the flight has a start time.
+    pub timestamp: Option<i64>,
+    /// Exhaust gas temperature per cylinder, cylinder 1 first.
+    pub egt: Vec<i32>,
+    /// Cylinder head temperature per cylinder, cylinder 1 first.
+    pub cht: Vec<i32>,
+    /// Turbine inlet temperatures.
+    pub tit1: Option<i32>,
+    pub tit2: Option<i32>,
+    pub oil_temp: Option<i32>,
+    /// Oil pressure in psi.
+    pub oil_pressure: Option<i32>,
+    /// Compressor discharge temperature.
+    pub cdt: Option<i32>,
+    /// Induction air temperature.
+    pub iat: Option<i32>,
+    /// Outside air temperature.
+    pub oat: Option<i32>,
+    pub volts: Option<f64>,
+    /// Fuel flow in the `$F` units per hour.
+    pub fuel_flow: Option<f64>,
+    /// Fuel used totalizer, which resets when the tanks are filled.
+    pub fuel_used: Option<f64>,
+    /// Manifold pressure in inches of mercury.
+    pub map: Option<f64>,
+    pub rpm: Option<i32>,
+    /// Raw status field. 2 marks where the pilot pressed MARK and 3 where
+    /// the marked span ended; 8 is seen over long stretches and is not
+    /// understood. No value is known to flag fast recording, so timestamps
+    /// step by the flight header's interval throughout.
+    pub mark: i32,
+}
+
+// Position of each channel in the decoded field array
+pub(crate) const EGT: usize = 0;
+const TIT1: usize = 6;
+const TIT2: usize = 7;
+const CHT: usize = 8;
+const OIL_TEMP: usize = 15;
+const MARK: usize = 16;
+const OIL_PRESSURE: usize = 17;
+const CDT: usize = 18;
+const IAT: usize = 19;
+const VOLTS: usize = 20;
+const OAT: usize = 21;
+const FUEL_USED: usize = 22;
+const FUEL_FLOW: usize = 23;
+const MAP: usize = 40;
+pub(crate) const RPM: usize = 41;
+pub(crate) const RPM_HIGH: usize = 42;
+// Field 48 of the EDM-900 sample reads about 20 in cruise, 35 just after
+// start and 0 after shutdown, as a charge current would. It is not decoded
+// until a download with a known current shunt shows its flag bit and sign.
+// No `$C` flag or field for the mag check has been seen either, nor for an
+// exhaust probe beyond TIT1 and TIT2; a manifold probe set up as TIT2 reads
+// as `Tit2`. Any field without a name here is still framed by its decode
+// flag bit, so one that is recorded cannot shift the channels after it.
+
+/// The field array only has room for six cylinders per engine.
+pub(crate) const MAX_CYLINDERS: i32 = 6;
+pub(crate) const FIELDS: usize = 112;
+/// Every field starts here, so the first record is a delta like any other.
+/// Each flight starts again from this value, so no flight depends on an
+/// earlier one or on a previous download.
+pub(crate) const INITIAL_VALUE: i32 = 0xF0;
+/// Decode flag bits announcing a high-byte flag byte rather than a field flag byte.
+pub(crate) const SCALE_BITS: [u16; 2] = [6, 7];
+/// First field each high-byte flag byte applies to.
+pub(crate) const SCALE_FIELDS: [usize; 2] = [EGT, 24];
+
+/// Joins each flight to the one before it when it starts within
+/// `max_gap_secs` of that flight's last record, as happens when a power
+/// blip splits one flight in two. Both must have been recorded at the same
+/// interval with the same sensors. The joined flight keeps the first flight's number, header and
+/// offset, and the records keep their own timestamps.
+pub fn merge_flights(flights: Vec<Flight>, max_gap_secs: i64) -> Vec<Flight> {
+    let mut merged: Vec<Flight> = Vec::with_capacity(flights.len());
+    for flight in flights {
+        if let Some(previous) = merged.last_mut() {
+            let end = previous.records.last().and_then(|r| r.timestamp);
+            let start = flight.records.first().and_then(|r| r.timestamp);
+            let gap = start.zip(end).map(|(start, end)| start - end);
+            let same_interval = previous.recording_interval_secs().is_some()
+                && previous.recording_interval_secs() == flight.recording_interval_secs();
+            // Records are laid out by the first flight's header
+            let same_sensors = previous.header.as_ref().map(|h| &h.sensors)
+                == flight.header.as_ref().map(|h| &h.sensors);
+            if same_interval
+                && same_sensors
+                && gap.is_some_and(|gap| (0..=max_gap_secs).contains(&gap))
+            {
+                previous.words += flight.words;
+                previous.samples = match (previous.samples, flight.samples) {
+                    (None, None) => None,
+                    (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+                };
+                previous.records.extend(flight.records);
+                previous.diagnostics.extend(flight.diagnostics);
+                continue;
+            }
+        }
+        merged.push(flight);
+    }
+    merged
+}
+
+impl FlightHeader {
+    /// Header length in words for the EDM-700/800 family.
+    pub(crate) const WORDS: usize = 7;
+    /// Header length in words for the EDM-900 and newer, which add GPS and
+    /// configuration words ahead of the timing fields.
+    pub(crate) const LONG_WORDS: usize = 14;
+
+    /// Parses the header, returning it with its length in bytes
+    /// including the trailing checksum.
+    pub(crate) fn parse(block: &[u8], long: bool) -> Option<(FlightHeader, usize)> {
+        let words = if long {
+            FlightHeader::LONG_WORDS
+        } else {
+            FlightHeader::WORDS
+        };
+        let bytes = block.get(..words * 2 + 1)?;
+        // The checksum byte is the negated sum of the header
+        if bytes.iter().fold(0u8, |sum, x| sum.wrapping_add(*x)) != 0 {
+            return None;
+        }
+
+        let word = |i: usize| u16::from_be_bytes([bytes[i * 2], bytes[i * 2 + 1]]);
+        let flags = word(1) as u32 | (word(2) as u32) << 16;
+        Some((
+            FlightHeader {
+                number: word(0) as i32,
+                flags,
+                sensors: Sensors::from_flags(flags),
+                interval_secs: word(words - 3) as i32,
+                start_time: FlightHeader::start_time(word(words - 2), word(words - 1)),
+            },
+            bytes.len(),
+        ))
+    }
+
+    /// Date and time are packed like FAT timestamps, with years counted from 2000.
+    fn start_time(date: u16, time: u16) -> Option<i64> {
+        let day = (date & 0x1f) as u32;
+        let month = (date >> 5 & 0x0f) as u32;
+        if day == 0 || month == 0 {
+            return None;
+        }
+        Some(epoch_seconds(
+            2000 + (date >> 9) as i64,
+            month,
+            day,
+            (time >> 11) as u32,
+            (time >> 5 & 0x3f) as u32,
+            (time & 0x1f) as u32 * 2,
+        ))
+    }
+}
+
+impl Record {
+    fn from_values(values: &[i32; FIELDS], sensors: &Sensors, timestamp: Option<i64>) -> Record {
+        let channel = |present: bool, field: usize| present.then_some(values[field]);
+        let tenths = |present: bool, field: usize| present.then_some(values[field] as f64 / 10.0);
+        let cylinders = |count: Option<i32>| count.unwrap_or(0).clamp(0, MAX_CYLINDERS) as usize;
+        Record {
+            timestamp,
+            egt: values[EGT..EGT + cylinders(sensors.egt_count)].to_vec(),
+            cht: values[CHT..CHT + cylinders(sensors.cht_count)].to_vec(),
+            tit1: channel(sensors.tit1, TIT1),
+            tit2: channel(sensors.tit2, TIT2),
+            oil_temp: channel(sensors.oil_temp, OIL_TEMP),
+            oil_pressure: channel(sensors.oil_pressure, OIL_PRESSURE),
+            cdt: channel(sensors.cdt, CDT),
+            iat: channel(sensors.iat, IAT),
+            oat: channel(sensors.oat, OAT),
+            volts: tenths(sensors.volts, VOLTS),
+            fuel_flow: tenths(sensors.fuel_flow, FUEL_FLOW),
+            fuel_used: tenths(sensors.fuel_flow, FUEL_USED),
+            map: tenths(sensors.map, MAP),
+            rpm: channel(sensors.rpm, RPM),
+            mark: values[MARK],
+        }
+    }
+
+    /// Whether the pilot marked an event at this record.
+    pub fn is_marked(&self) -> bool {
+        self.mark & 0x02 != 0
+    }
+
+    /// The value of one channel, scaled to its units.
+    pub fn get(&self, channel: Channel) -> Option<f64> {
+        let cylinder = |values: &[i32], n: u8| values.get((n as usize).checked_sub(1)?).copied();
+        match channel {
+            Channel::Egt(n) => cylinder(&self.egt, n).map(f64::from),
+            Channel::Cht(n) => cylinder(&self.cht, n).map(f64::from),
+            Channel::Tit1 => self.tit1.map(f64::from),
+            Channel::Tit2 => self.tit2.map(f64::from),
+            Channel::OilTemp => self.oil_temp.map(f64::from),
+            Channel::OilPressure => self.oil_pressure.map(f64::from),
+            Channel::Cdt => self.cdt.map(f64::from),
+            Channel::Iat => self.iat.map(f64::from),
+            Channel::Volts => self.volts,
+            Channel::Oat => self.oat.map(f64::from),
+            Channel::FuelUsed => self.fuel_used,
+            Channel::FuelFlow => self.fuel_flow,
+            Channel::Map => self.map,
+            Channel::Rpm => self.rpm.map(f64::from),
+        }
+    }
+
+    /// Sets one channel from a value scaled as `get` gives it, growing the
+    /// cylinder lists as needed.
+    pub(crate) fn set(&mut self, channel: Channel, value: f64) {
+        let cylinder = |values: &mut Vec<i32>, n: u8| {
+            let n = usize::from(n.max(1));
+            if values.len() < n {
+                values.resize(n, 0);
+            }
+            values[n - 1] = value.round() as i32;
+        };
+        let whole = Some(value.round() as i32);
+        match channel {
+            Channel::Egt(n) => cylinder(&mut self.egt, n),
+            Channel::Cht(n) => cylinder(&mut self.cht, n),
+            Channel::Tit1 => self.tit1 = whole,
+            Channel::Tit2 => self.tit2 = whole,
+            Channel::OilTemp => self.oil_temp = whole,
+            Channel::OilPressure => self.oil_pressure = whole,
+            Channel::Cdt => self.cdt = whole,
+            Channel::Iat => self.iat = whole,
+            Channel::Volts => self.volts = Some(value),
+            Channel::Oat => self.oat = whole,
+            Channel::FuelUsed => self.fuel_used = Some(value),
+            Channel::FuelFlow => self.fuel_flow = Some(value),
+            Channel::Map => self.map = Some(value),
+            Channel::Rpm => self.rpm = whole,
+        }
+    }
+
+    /// The value of one channel as the integer the instrument stored,
+    /// before it was scaled by `DecodeConfig::decimals`. `config` is the
+    /// one the record was decoded with.
+    pub fn raw(&self, channel: Channel, config: &DecodeConfig) -> Option<i64> {
+        let scale = 10f64.powi(config.decimals(channel) as i32);
+        self.get(channel).map(|v| (v * scale).round() as i64)
+    }
+
+    /// One value of `channel` as exported, to the precision it was
+    /// recorded at in a file decoded with `config`, or an empty string if
+    /// the record has none.
+    fn format(&self, channel: Channel, config: &DecodeConfig) -> String {
+        self.get(channel)
+            .map(|v| format!("{v:.*}", config.decimals(channel)))
+            .unwrap_or_default()
+    }
+
+    /// Like `format`, converted by `Channel::to_si` to at least one decimal
+    /// place.
+    fn format_si(&self, channel: Channel, config: &DecodeConfig) -> String {
+        match self.get(channel).map(|v| (v, channel.to_si(v))) {
+            Some((_, Some(si))) => format!("{si:.*}", config.decimals(channel).max(1)),
+            _ => self.format(channel, config),
+        }
+    }
+
+    /// The record as a row of strings lined up with `labels`, which are
+    /// `Channel::label`s or `MARK`, for tabular output. A label this record
+    /// holds no value for, or does not know, gives an empty string.
+    pub fn to_row(&self, labels: &[&str]) -> Vec<String> {
+        self.to_row_with(labels, &ExportOptions::default())
+    }
+
+    /// Like `to_row`, with values written as `options` asks: raw, in SI
+    /// units or to the precision of `options.decode`. This is how
+    /// `Flight::write_csv_with` writes them.
+    pub fn to_row_with(&self, labels: &[&str], options: &ExportOptions) -> Vec<String> {
+        labels
+            .iter()
+            .map(|label| match Channel::from_label(label) {
+                Some(channel) if options.raw_values => self
+                    .raw(channel, &options.decode)
+                    .map(|raw| raw.to_string())
+                    .unwrap_or_default(),
+                Some(channel) if options.si_units => self.format_si(channel, &options.decode),
+                Some(channel) => self.format(channel, &options.decode),
+                None if label.eq_ignore_ascii_case("MARK") => {
+                    u8::from(self.is_marked()).to_string()
+                }
+                None => String::new(),
+            })
+            .collect()
+    }
+
+    /// Every channel this record holds a value for, in decode order.
+    pub fn channels(&self) -> Vec<Channel> {
+        let mut channels = (1..=self.egt.len() as u8)
+            .map(Channel::Egt)
+            .chain((1..=self.cht.len() as u8).map(Channel::Cht))
+            .chain([
+                Channel::Tit1,
+                Channel::Tit2,
+                Channel::OilTemp,
+                Channel::OilPressure,
+                Channel::Cdt,
+                Channel::Iat,
+                Channel::Volts,
+                Channel::Oat,
+                Channel::FuelUsed,
+                Channel::FuelFlow,
+                Channel::Map,
+                Channel::Rpm,
+            ])
+            .filter(|channel| self.get(*channel).is_some())
+            .collect::<Vec<Channel>>();
+        channels.sort();
+        channels
+    }
+
+    /// Whether `channel` holds a reading no working probe could produce.
+    pub fn is_fault(&self, channel: Channel) -> bool {
+        match (self.get(channel), channel.plausible_range()) {
+            (Some(value), Some(range)) => !range.contains(&value),
+            _ => false,
+        }
+    }
+
+    /// Channels whose reading looks like a probe fault.
+    pub fn faults(&self) -> Vec<Channel> {
+        self.channels()
+            .into_iter()
+            .filter(|channel| self.is_fault(*channel))
+            .collect()
+    }
+
+    /// The field array this record decodes from, carrying channels the
+    /// record lacks over from `previous`.
+    pub(crate) fn to_values(&self, previous: &[i32; FIELDS]) -> [i32; FIELDS] {
+        let mut values = *previous;
+        let mut set = |field: usize, value: Option<i32>| {
+            if let Some(value) = value {
+                values[field] = value;
+            }
+        };
+        let tenths = |value: Option<f64>| value.map(|v| (v * 10.0).round() as i32);
+        for (i, egt) in self.egt.iter().enumerate() {
+            set(EGT + i, Some(*egt));
+        }
+        for (i, cht) in self.cht.iter().enumerate() {
+            set(CHT + i, Some(*cht));
+        }
+        set(TIT1, self.tit1);
+        set(TIT2, self.tit2);
+        set(OIL_TEMP, self.oil_temp);
+        set(OIL_PRESSURE, self.oil_pressure);
+        set(CDT, self.cdt);
+        set(IAT, self.iat);
+        set(OAT, self.oat);
+        set(VOLTS, tenths(self.volts));
+        set(FUEL_FLOW, tenths(self.fuel_flow));
+        set(FUEL_USED, tenths(self.fuel_used));
+        set(MAP, tenths(self.map));
+        set(RPM, self.rpm);
+        set(MARK, Some(self.mark));
+        values
+    }
+}
+
+impl Flight {
+    /// Seconds between records, from the flight header. `$C` does not hold
+    /// the interval, and it can change between flights.
+    pub fn recording_interval_secs(&self) -> Option<i32> {
+        self.header.as_ref().map(|h| h.interval_secs)
+    }
+
+    /// Number of records in the flight, from `skim_flights` if that is how
+    /// it was read, or else the decoded records.
+    pub fn sample_count(&self) -> usize {
+        self.samples.unwrap_or(self.records.len())
+    }
+
+    /// Time from the first record to the last, from `sample_count` and the
+    /// recording interval.
+    pub fn duration(&self) -> Option<Duration> {
+        let interval = u64::try_from(self.recording_interval_secs()?).ok()?;
+        let intervals = self.sample_count().checked_sub(1)? as u64;
+        Some(Duration::from_secs(interval * intervals))
+    }
+
+    /// Every record the pilot marked, as its timestamp and its index in
+    /// `records`, in flight order.
+    pub fn mark_events(&self) -> Vec<(Option<i64>, usize)> {
+        self.records
+            .iter()
+            .enumerate()
+            .filter(|(_, record)| record.is_marked())
+            .map(|(index, record)| (record.timestamp, index))
+            .collect()
+    }
+
+    /// EGT probes this flight recorded, from its own flag word. That can be
+    /// fewer than `$C` configures when a probe was turned off for the
+    /// flight. `None` until the flight header has been read.
+    pub fn active_egt_count(&self) -> Option<usize> {
+        let count = self.header.as_ref()?.sensors.egt_count?;
+        Some(count.clamp(0, MAX_CYLINDERS) as usize)
+    }
+
+    /// CHT probes this flight recorded, as `active_egt_count` counts EGTs.
+    pub fn active_cht_count(&self) -> Option<usize> {
+        let count = self.header.as_ref()?.sensors.cht_count?;
+        Some(count.clamp(0, MAX_CYLINDERS) as usize)
+    }
+
+    /// One channel, named by its `Channel::label`, as (seconds since the
+    /// first record, value) pairs for plotting. Records without a timestamp
+    /// or a value are left out. Returns `None` for an unknown name or a
+    /// channel no record holds.
+    pub fn channel_series(&self, name: &str) -> Option<Vec<(f64, f64)>> {
+        let channel = Channel::from_label(name)?;
+        let start = self.records.iter().find_map(|r| r.timestamp)?;
+        let series = self
+            .records
+            .iter()
+            .filter_map(|r| Some(((r.timestamp? - start) as f64, r.get(channel)?)))
+            .collect::<Vec<(f64, f64)>>();
+        (!series.is_empty()).then_some(series)
+    }
+
+    /// Decodes this flight from the start of `block`, returning the number
+    /// of bytes it occupied.
+    pub(crate) fn decode(
+        &mut self,
+        block: &[u8],
+        config: Option<&Sensors>,
+        long_header: bool,
+        options: &DecodeConfig,
+    ) -> Result<usize, ParseError> {
+        let end = (self.words * 2).min(block.len());
+        if end < self.words * 2 {
+            self.diagnostics.push(Diagnostic::TruncatedFlight {
+                flight: self.number,
+                expected_bytes: self.words * 2,
+                found_bytes: end,
+            });
+        }
+        let Some((header, mut pos)) = FlightHeader::parse(block, long_header) else {
+            self.diagnostics.push(Diagnostic::BadRecord {
+                flight: self.number,
+                offset: 0,
+            });
+            return Ok(end);
+        };
+
+        // The flight's own flags say what was actually recorded
+        if let Some(config) = config {
+            let counts = |sensors: &Sensors| {
+                (
+                    sensors.egt_count.unwrap_or(0),
+                    sensors.cht_count.unwrap_or(0),
+                )
+            };
+            let (configured, recorded) = (counts(config), counts(&header.sensors));
+            if options.strict_probe_counts && configured != recorded {
+                return Err(ParseError::ProbeCountMismatch {
+                    flight: self.number,
+                    egt: (configured.0, recorded.0),
+                    cht: (configured.1, recorded.1),
+                });
+            }
+            if *config != header.sensors {
+                self.diagnostics.push(Diagnostic::SensorMismatch {
+                    flight: self.number,
+                    config: config.clone(),
+                    recorded: header.sensors.clone(),
+                });
+            }
+        }
+
+        let mut values = [INITIAL_VALUE; FIELDS];
+        // `$D` counts whole words, so an odd-length block ends one byte into
+        // the next flight and the next block starts after the last record
+        while pos + 1 < end && !Flight::is_padding(&block[pos..end]) {
+            let previous = values;
+            let Some((next, repeat)) = Flight::decode_record(&block[..end], pos, &mut values)
+            else {
+                self.diagnostics.push(Diagnostic::BadRecord {
+                    flight: self.number,
+                    offset: pos,
+                });
+                pos = end;
+                break;
+            };
+            // Stable readings are stored once with a count of how many more
+            // samples held the same values
+            if self.records.is_empty() {
+                if repeat > 0 {
+                    self.diagnostics.push(Diagnostic::LeadingRepeat {
+                        flight: self.number,
+                        count: repeat,
+                    });
+                }
+            } else {
+                for _ in 0..repeat {
+                    self.push_record(&previous, &header, options)?;
+                }
+            }
+            self.push_record(&values, &header, options)?;
+            pos = next;
+        }
+        self.header = Some(header);
+        Ok(pos)
+    }
+
+    /// Reads this flight's header from the start of `block` and counts its
+    /// records, returning the number of bytes it occupied. Records are only
+    /// framed, not turned into `Record`s, and the count matches `decode`.
+    pub(crate) fn skim(&mut self, block: &[u8], long_header: bool) -> usize {
+        let end = (self.words * 2).min(block.len());
+        let Some((header, mut pos)) = FlightHeader::parse(block, long_header) else {
+            return end;
+        };
+        let mut values = [INITIAL_VALUE; FIELDS];
+        let mut samples = 0;
+        while pos + 1 < end && !Flight::is_padding(&block[pos..end]) {
+            let Some((next, repeat)) = Flight::decode_record(&block[..end], pos, &mut values)
+            else {
+                pos = end;
+                break;
+            };
+            if samples > 0 {
+                samples += repeat as usize;
+            }
+            samples += 1;
+            pos = next;
+        }
+        self.header = Some(header);
+        self.samples = Some(samples);
+        pos
+    }
+
+    /// Decodes only the first record of the flight at the start of `block`,
+    /// which holds absolute values rather than changes.
+    pub(crate) fn first_record(block: &[u8], long_header: bool) -> Option<Record> {
+        let (header, pos) = FlightHeader::parse(block, long_header)?;
+        let mut values = [INITIAL_VALUE; FIELDS];
+        Flight::decode_record(block, pos, &mut values)?;
+        Some(Record::from_values(
+            &values,
+            &header.sensors,
+            header.start_time,
+        ))
+    }
+
+    fn push_record(
+        &mut self,
+        values: &[i32; FIELDS],
+        header: &FlightHeader,
+        options: &DecodeConfig,
+    ) -> Result<(), ParseError> {
+        if let Some(limit) = options.max_records_per_flight {
+            if self.records.len() >= limit {
+                return Err(ParseError::RecordLimit {
+                    flight: self.number,
+                    limit,
+                });
+            }
+        }
+        let timestamp = header
+            .start_time
+            .map(|start| start + self.records.len() as i64 * header.interval_secs as i64);
+        let mut record = Record::from_values(values, &header.sensors, timestamp);
+        if options.high_resolution_fuel_flow {
+            // From the stored integer, so 98 is exactly 0.98
+            record.fuel_flow = record.fuel_flow.map(|ff| (ff * 10.0).round() / 100.0);
+        }
+        self.records.push(record);
+        Ok(())
+    }
+
+    /// Applies the record at `pos` to `values`, returning where the next
+    /// record starts and how many times the previous record repeats before it.
+    ///
+    /// A record is a decode flag word (written twice), a repeat count, the
+    /// field flag bytes the decode flags announce, a sign byte for each field
+    /// flag byte, one delta byte per set field flag bit, and a checksum.
+    ///
+    /// The decode flag word is big endian and read from its lowest bit up.
+    /// Bits 0-5 announce field flag bytes 0-5, bits 6 and 7 the high-byte
+    /// flag bytes for fields 0-7 and 24-31, and bits 8-15 field flag bytes
+    /// 6-13. Bit `i` of field flag byte `n`, and of its sign byte, is field
+    /// `n * 8 + i`. A high byte takes its sign from its field's sign byte,
+    /// so it is only meaningful alongside that field flag byte.
+    fn decode_record(block: &[u8], pos: usize, values: &mut [i32; FIELDS]) -> Option<(usize, u8)> {
+        let mut bytes = block.get(pos..)?.iter().copied();
+        let mut next = || bytes.next();
+
+        let decode_flags = u16::from_be_bytes([next()?, next()?]);
+        if u16::from_be_bytes([next()?, next()?]) != decode_flags {
+            return None;
+        }
+        let repeat = next()?;
+
+        let present = (0..16)
+            .filter(|bit| decode_flags >> bit & 1 == 1)
+            .collect::<Vec<u16>>();
+        let flag_bytes = present
+            .iter()
+            .map(|_| next())
+            .collect::<Option<Vec<u8>>>()?;
+        let mut signs = [0u8; FIELDS / 8];
+        for bit in present.iter().filter(|bit| !SCALE_BITS.contains(bit)) {
+            signs[Flight::field_byte(*bit)] = next()?;
+        }
+
+        let mut deltas = [0i32; FIELDS];
+        for (bit, flag_byte) in present.iter().zip(&flag_bytes) {
+            for i in (0..8).filter(|i| flag_byte >> i & 1 == 1) {
+                let delta = next()? as i32;
+                match SCALE_BITS.iter().position(|scale| scale == bit) {
+                    Some(scale) => deltas[SCALE_FIELDS[scale] + i] += delta << 8,
+                    None => deltas[Flight::field_byte(*bit) * 8 + i] += delta,
+                }
+            }
+        }
+        let _checksum = next()?;
+
+        let length = block.len() - pos - bytes.len();
+        let record = &block[pos..pos + length];
+        // Like the flight header, the checksum is the negated sum of the record
+        if record.iter().fold(0u8, |sum, x| sum.wrapping_add(*x)) != 0 {
+            return None;
+        }
+
+        // RPM is too wide for one byte, its high byte is carried in the next field
+        deltas[RPM] += deltas[RPM_HIGH] << 8;
+        deltas[RPM_HIGH] = 0;
+
+        for (field, delta) in deltas.iter().enumerate() {
+            if signs[field / 8] >> (field % 8) & 1 == 1 {
+                values[field] -= delta;
+            } else {
+                values[field] += delta;
+            }
+        }
+        Some((pos + length, repeat))
+    }
+
+    /// Whether the rest of a block is zero bytes after the last record,
+    /// apart from the last byte, which may be the start of the next flight
+    /// when the block has an odd number of bytes.
+    ///
+    /// Zeroes would frame as records with no changes, adding copies of the
+    /// last record. The EDM never writes a record without decode flags, so
+    /// a zero flag word marks the end of the data.
+    fn is_padding(rest: &[u8]) -> bool {
+        rest.iter().rev().skip(1).all(|b| *b == 0)
+    }
+
+    /// Index of the field flag byte a decode flag bit announces.
+    pub(crate) fn field_byte(bit: u16) -> usize {
+        if bit < SCALE_BITS[0] {
+            bit as usize
+        } else {
+            bit as usize - SCALE_BITS.len()
+        }
+    }
+}
@@ -0,0 +1,1022 @@
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt, fs,
+    path::Path,
+    str,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    channel::Channel,
+    config::DecodeConfig,
+    diagnostic::Diagnostic,
+    error::{ExportError, ParseError},
+    flight::{merge_flights, Flight, FlightHeader, Record, MAX_CYLINDERS},
+    time::{epoch_seconds, format_timestamp},
+};
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Alarms {
+    pub min_volts: Option<f64>,
+    pub max_volts: Option<f64>,
+    pub max_egt_spread: Option<i32>,
+    pub max_cht: Option<i32>,
+    pub max_cht_cool_rate: Option<i32>,
+    pub max_egt: Option<i32>,
+    pub min_oil_temp: Option<i32>,
+    pub max_oil_temp: Option<i32>,
+    /// Whether `$A` gave the volts limits in tenths rather than whole volts.
+    pub volts_in_tenths: bool,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+
+pub enum FuelFlowUnits {
+    GPH,
+    PPH,
+    LPH,
+    KPH,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TempUnits {
+    Farenheit,
+    Celcius,
+}
+
+/// Fuel setup from `$F`, which is not decoded yet.
+///
+/// No download seen so far holds the totalizer's fuel remaining at the
+/// start of a flight, here or in the flight header, so only fuel used is
+/// known. The instrument keeps the remaining quantity to itself.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Fuel {
+    pub fuel_flow_units: Option<FuelFlowUnits>,
+    pub full_level: Option<i32>,
+    pub warning_level: Option<i32>,
+    pub k_factor_ff1: Option<i32>,
+    pub k_factor_ff2: Option<i32>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Sensors {
+    pub egt_count: Option<i32>,
+    pub cht_count: Option<i32>,
+    pub volts: bool,
+    pub oil_temp: bool,
+    pub oil_pressure: bool,
+    pub tit1: bool,
+    pub tit2: bool,
+    pub oat: bool,
+    pub fuel_flow: bool,
+    pub iat: bool,
+    pub cdt: bool,
+    pub map: bool,
+    pub rpm: bool,
+}
+
+impl Sensors {
+    const VOLTS: u32 = 1 << 0;
+    const CHT_SHIFT: u32 = 2;
+    const EGT_SHIFT: u32 = 11;
+    const CYLINDER_MASK: u32 = 0x1ff;
+    const OIL_TEMP: u32 = 1 << 20;
+    const TIT1: u32 = 1 << 21;
+    const TIT2: u32 = 1 << 22;
+    const CDT: u32 = 1 << 23;
+    const IAT: u32 = 1 << 24;
+    const OAT: u32 = 1 << 25;
+    const RPM: u32 = 1 << 26;
+    const FUEL_FLOW: u32 = 1 << 27;
+    const OIL_PRESSURE: u32 = 1 << 28;
+    const MAP: u32 = 1 << 30;
+
+    /// Sensors enabled by a 32 bit flag word, as found in `$C` and in each
+    /// flight header. Each cylinder gets its own EGT and CHT bit.
+    pub fn from_flags(flags: u32) -> Sensors {
+        let cylinders = |shift: u32| (flags >> shift & Sensors::CYLINDER_MASK).count_ones() as i32;
+        Sensors {
+            egt_count: Some(cylinders(Sensors::EGT_SHIFT)),
+            cht_count: Some(cylinders(Sensors::CHT_SHIFT)),
+            volts: flags & Sensors::VOLTS != 0,
+            oil_temp: flags & Sensors::OIL_TEMP != 0,
+            oil_pressure: flags & Sensors::OIL_PRESSURE != 0,
+            tit1: flags & Sensors::TIT1 != 0,
+            tit2: flags & Sensors::TIT2 != 0,
+            oat: flags & Sensors::OAT != 0,
+            fuel_flow: flags & Sensors::FUEL_FLOW != 0,
+            iat: flags & Sensors::IAT != 0,
+            cdt: flags & Sensors::CDT != 0,
+            map: flags & Sensors::MAP != 0,
+            rpm: flags & Sensors::RPM != 0,
+        }
+    }
+
+    /// Number of sensors fitted: every EGT and CHT probe plus each enabled
+    /// single channel. Fuel flow counts once, although `present` also lists
+    /// the fuel used it drives.
+    pub fn channel_count(&self) -> usize {
+        let probes = |count: Option<i32>| count.unwrap_or(0).max(0) as usize;
+        probes(self.egt_count)
+            + probes(self.cht_count)
+            + [
+                self.volts,
+                self.oil_temp,
+                self.oil_pressure,
+                self.tit1,
+                self.tit2,
+                self.oat,
+                self.fuel_flow,
+                self.iat,
+                self.cdt,
+                self.map,
+                self.rpm,
+            ]
+            .iter()
+            .filter(|enabled| **enabled)
+            .count()
+    }
+
+    /// Channels these sensors record, in decode order.
+    pub fn present(&self) -> Vec<Channel> {
+        let cylinders = |count: Option<i32>| 1..=count.unwrap_or(0).clamp(0, MAX_CYLINDERS) as u8;
+        let mut channels = cylinders(self.egt_count)
+            .map(Channel::Egt)
+            .collect::<Vec<Channel>>();
+        channels.extend(
+            [(self.tit1, Channel::Tit1), (self.tit2, Channel::Tit2)]
+                .into_iter()
+                .filter_map(|(present, channel)| present.then_some(channel)),
+        );
+        channels.extend(cylinders(self.cht_count).map(Channel::Cht));
+        channels.extend(
+            [
+                (self.oil_temp, Channel::OilTemp),
+                (self.oil_pressure, Channel::OilPressure),
+                (self.cdt, Channel::Cdt),
+                (self.iat, Channel::Iat),
+                (self.volts, Channel::Volts),
+                (self.oat, Channel::Oat),
+                (self.fuel_flow, Channel::FuelUsed),
+                (self.fuel_flow, Channel::FuelFlow),
+                (self.map, Channel::Map),
+                (self.rpm, Channel::Rpm),
+            ]
+            .into_iter()
+            .filter_map(|(present, channel)| present.then_some(channel)),
+        );
+        channels
+    }
+
+    /// Export labels for the channels these sensors record, in decode order.
+    pub fn field_labels(&self) -> Vec<String> {
+        self.present().iter().map(Channel::label).collect()
+    }
+
+    /// Resolves column names, as given to `--columns`, to the channels they
+    /// cover. A name is one of `field_labels`, or `EGT` or `CHT` for every
+    /// cylinder, in any case. The result is in decode order.
+    pub fn select_channels<S: AsRef<str>>(&self, names: &[S]) -> Result<Vec<Channel>, ExportError> {
+        let present = self.present();
+        let mut selected = Vec::new();
+        for name in names {
+            let name = name.as_ref().trim().to_ascii_uppercase();
+            let matches = present
+                .iter()
+                .filter(|channel| match (name.as_str(), channel) {
+                    ("EGT", Channel::Egt(_)) | ("CHT", Channel::Cht(_)) => true,
+                    _ => channel.label() == name,
+                })
+                .collect::<Vec<&Channel>>();
+            if matches.is_empty() {
+                return Err(ExportError::UnknownColumn {
+                    name,
+                    available: self.field_labels(),
+                });
+            }
+            selected.extend(matches);
+        }
+        selected.sort();
+        selected.dedup();
+        Ok(selected)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Features {
+    pub model: Option<i32>,
+    pub firmware_version: Option<i32>,
+    pub sensors: Option<Sensors>,
+    pub engine_temperature_unit: Option<TempUnits>,
+    pub unknown1: Option<i32>,
+    pub unknown2: Option<i32>,
+}
+
+/// Parses the header of a JPI file held in memory.
+///
+/// ```
+/// use jpi::HeaderData;
+///
+/// let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/4cyl.jpi");
+/// let bytes = std::fs::read(path)?;
+/// let header = HeaderData::try_from(&bytes[..])?;
+/// assert_eq!(header.registration.as_deref(), Some("N75278"));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+impl TryFrom<&[u8]> for HeaderData {
+    type Error = ParseError;
+
+    fn try_from(bytes: &[u8]) -> Result<HeaderData, ParseError> {
+        EdmHeader::from_bytes(bytes.to_vec()).try_data()
+    }
+}
+
+impl Features {
+    /// Whether flight blocks start with the 14 word header of the EDM-900
+    /// and later, rather than the 7 word header of older models.
+    pub fn long_flight_header(&self) -> bool {
+        self.model.is_some_and(|m| m >= 900)
+    }
+
+    /// The name the instrument is sold under, such as `EDM-900`. A model
+    /// number not in the list is given as the bare number.
+    pub fn model_name(&self) -> Option<Cow<'static, str>> {
+        let model = self.model?;
+        Some(match model {
+            700 => "EDM-700".into(),
+            711 => "EDM-711".into(),
+            730 => "EDM-730".into(),
+            740 => "EDM-740".into(),
+            760 => "EDM-760".into(),
+            800 => "EDM-800".into(),
+            830 => "EDM-830".into(),
+            900 => "EDM-900".into(),
+            930 => "EDM-930".into(),
+            960 => "EDM-960".into(),
+            _ => model.to_string().into(),
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HeaderData {
+    pub registration: Option<String>,
+    /// Instrument serial number from `$H`, when the firmware records one.
+    pub serial_number: Option<String>,
+    pub alarms: Option<Alarms>,
+    pub fuel: Option<Fuel>,
+    /// When the data was downloaded from the EDM, in seconds since the Unix
+    /// epoch. The EDM clock has no time zone, so this is the local time of
+    /// the instrument read as UTC.
+    pub download_time: Option<i64>,
+    /// Download protocol version from `$P`, a single number rather than a
+    /// block of limits; redlines are only known from `$A` alarms. It is
+    /// read for reporting only. Every file seen so far is version 2, and
+    /// what does differ between files, such as the flight header length,
+    /// follows the `$C` model (`Features::long_flight_header`), so no
+    /// decoder behaviour is known to depend on it.
+    pub protocol_version: Option<i32>,
+    pub features: Option<Features>,
+    pub flights: Vec<Flight>,
+    /// Problems found in the header lines themselves.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// One field that differs between two headers, from `HeaderData::diff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// One `$` line of the header, from `EdmHeader::header_lines`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderLine {
+    /// Line number in the header, starting at 1.
+    pub number: usize,
+    pub tag: char,
+    /// The comma separated fields after the tag, trimmed and unquoted.
+    pub fields: Vec<String>,
+    /// Whether the checksum matched, or `None` if the line has none.
+    pub checksum: Option<bool>,
+}
+
+/// The layout choices a header led to, from `HeaderData::applied_profile`.
+///
+/// Only choices made from the header are listed. High resolution fuel
+/// flow comes from `DecodeConfig`, and `$T` is always read month first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    /// `Features::model_name`.
+    pub model: Option<String>,
+    pub firmware_version: Option<i32>,
+    /// Flights start with the 14 word header rather than the 7 word one.
+    pub long_flight_header: bool,
+    /// Whether the `$A` volts limits were read as tenths, or `None` without
+    /// a `$A` line.
+    pub alarm_volts_in_tenths: Option<bool>,
+}
+
+impl fmt::Display for Profile {
+    /// For example `EDM-900 firmware 140: 14 word flight headers, alarm
+    /// volts in tenths`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.model.as_deref().unwrap_or("unknown model"))?;
+        if let Some(firmware) = self.firmware_version {
+            write!(f, " firmware {firmware}")?;
+        }
+        let words = if self.long_flight_header { 14 } else { 7 };
+        write!(f, ": {words} word flight headers")?;
+        match self.alarm_volts_in_tenths {
+            Some(true) => write!(f, ", alarm volts in tenths"),
+            Some(false) => write!(f, ", alarm volts in whole volts"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl HeaderData {
+    /// Flights listed in the header, without decoding any records.
+    pub fn flight_index(&self) -> &[Flight] {
+        &self.flights
+    }
+
+    /// Which model and firmware the header names and the layout quirks
+    /// that were chosen from them, for explaining how a file was read.
+    pub fn applied_profile(&self) -> Profile {
+        let features = self.features.as_ref();
+        Profile {
+            model: features
+                .and_then(|f| f.model_name())
+                .map(|name| name.into_owned()),
+            firmware_version: features.and_then(|f| f.firmware_version),
+            long_flight_header: features.is_some_and(|f| f.long_flight_header()),
+            alarm_volts_in_tenths: self.alarms.as_ref().map(|a| a.volts_in_tenths),
+        }
+    }
+
+    /// Whether everything an analysis relies on is there; see `missing`.
+    pub fn is_complete(&self) -> bool {
+        self.missing().is_empty()
+    }
+
+    /// The essential parts of the header that are absent: `registration`,
+    /// `config` for a missing `$C`, `flights` if none are listed, and
+    /// `checksums` if a line failed or lacked its checksum.
+    pub fn missing(&self) -> Vec<&'static str> {
+        let checksums = self.diagnostics.iter().any(|d| {
+            matches!(
+                d,
+                Diagnostic::BadHeaderChecksum { .. } | Diagnostic::MissingHeaderChecksum { .. }
+            )
+        });
+        [
+            ("registration", self.registration.is_none()),
+            ("config", self.features.is_none()),
+            ("flights", self.flights.is_empty()),
+            ("checksums", checksums),
+        ]
+        .into_iter()
+        .filter_map(|(part, absent)| absent.then_some(part))
+        .collect()
+    }
+
+    /// Every scalar field that is set, keyed by name. Alarm limits are keyed
+    /// `alarms.<field>` and the download time is formatted like
+    /// `format_timestamp`.
+    pub fn to_map(&self) -> BTreeMap<String, String> {
+        let mut map = BTreeMap::new();
+        let mut insert = |key: &str, value: Option<String>| {
+            if let Some(value) = value {
+                map.insert(key.to_string(), value);
+            }
+        };
+        insert("registration", self.registration.clone());
+        insert("serial_number", self.serial_number.clone());
+        insert("download_time", self.download_time.map(format_timestamp));
+        insert(
+            "protocol_version",
+            self.protocol_version.map(|v| v.to_string()),
+        );
+        if let Some(features) = &self.features {
+            insert("model", features.model.map(|v| v.to_string()));
+            insert(
+                "firmware_version",
+                features.firmware_version.map(|v| v.to_string()),
+            );
+        }
+        if let Some(alarms) = &self.alarms {
+            let limits = [
+                ("min_volts", alarms.min_volts.map(|v| v.to_string())),
+                ("max_volts", alarms.max_volts.map(|v| v.to_string())),
+                (
+                    "max_egt_spread",
+                    alarms.max_egt_spread.map(|v| v.to_string()),
+                ),
+                ("max_cht", alarms.max_cht.map(|v| v.to_string())),
+                (
+                    "max_cht_cool_rate",
+                    alarms.max_cht_cool_rate.map(|v| v.to_string()),
+                ),
+                ("max_egt", alarms.max_egt.map(|v| v.to_string())),
+                ("min_oil_temp", alarms.min_oil_temp.map(|v| v.to_string())),
+                ("max_oil_temp", alarms.max_oil_temp.map(|v| v.to_string())),
+            ];
+            for (name, value) in limits {
+                insert(&format!("alarms.{name}"), value);
+            }
+        }
+        map
+    }
+
+    /// Configuration fields that differ from `other`, using the names from
+    /// `to_map` plus `channels` for the sensors fitted. The download time
+    /// is not configuration and is left out.
+    pub fn diff(&self, other: &HeaderData) -> Vec<FieldChange> {
+        let config = |header: &HeaderData| {
+            let mut map = header.to_map();
+            map.remove("download_time");
+            if let Some(sensors) = header.features.as_ref().and_then(|f| f.sensors.as_ref()) {
+                map.insert("channels".to_string(), sensors.field_labels().join(" "));
+            }
+            map
+        };
+        let (before, after) = (config(self), config(other));
+        let mut fields = before.keys().chain(after.keys()).collect::<Vec<&String>>();
+        fields.sort();
+        fields.dedup();
+        fields
+            .into_iter()
+            .filter(|field| before.get(*field) != after.get(*field))
+            .map(|field| FieldChange {
+                field: field.clone(),
+                from: before.get(field).cloned(),
+                to: after.get(field).cloned(),
+            })
+            .collect()
+    }
+
+    /// The registration trimmed and in upper case, for matching against
+    /// other records of the aircraft. With `strip_prefix` the nationality
+    /// prefix is removed too: everything up to the hyphen for marks such as
+    /// `VH-ABC` or `G-ABCD`, or the `N` of a US mark such as `N123AB`.
+    pub fn registration_normalized(&self, strip_prefix: bool) -> Option<String> {
+        let registration = self.registration.as_deref()?.trim().to_ascii_uppercase();
+        if !strip_prefix {
+            return Some(registration);
+        }
+        if let Some((_, mark)) = registration.split_once('-') {
+            return Some(mark.to_string());
+        }
+        match registration.strip_prefix('N') {
+            Some(mark) if mark.starts_with(|c: char| c.is_ascii_digit()) => Some(mark.to_string()),
+            _ => Some(registration),
+        }
+    }
+
+    /// `download_time` as a `SystemTime`.
+    pub fn download_datetime(&self) -> Option<SystemTime> {
+        let secs = u64::try_from(self.download_time?).ok()?;
+        Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+    }
+}
+
+pub struct EdmHeader {
+    pub parsed: bool,
+    pub file_name: String,
+    pub file_stream: Vec<u8>,
+    pub data: Option<HeaderData>,
+    /// Where the binary data starts, if the caller already knew. Otherwise
+    /// the end of the header is found by scanning.
+    pub header_len: Option<usize>,
+}
+
+impl EdmHeader {
+    pub fn new(file_path: &str) -> Result<EdmHeader, Box<dyn std::error::Error>> {
+        Ok(EdmHeader {
+            file_name: file_path.to_string(),
+            file_stream: EdmHeader::read_file(file_path)?,
+            parsed: false,
+            data: None,
+            header_len: None,
+        })
+    }
+
+    /// Wraps a file that is already in memory.
+    pub fn from_bytes(bytes: Vec<u8>) -> EdmHeader {
+        EdmHeader {
+            file_name: String::new(),
+            file_stream: bytes,
+            parsed: false,
+            data: None,
+            header_len: None,
+        }
+    }
+
+    /// Wraps a file whose binary data is known to start at `header_len`,
+    /// skipping the search for the end of the header. Returns `None` unless
+    /// the byte before `header_len` ends a header line.
+    pub fn with_header_len(bytes: Vec<u8>, header_len: usize) -> Option<EdmHeader> {
+        if header_len == 0 || bytes.get(header_len - 1) != Some(&b'\n') {
+            return None;
+        }
+        Some(EdmHeader {
+            header_len: Some(header_len),
+            ..EdmHeader::from_bytes(bytes)
+        })
+    }
+
+    const START: u8 = b'$';
+    const END: u8 = b'*';
+    const DELIM: char = ',';
+    /// Tags that describe the whole file, so only their first line counts.
+    const SINGLE_TAGS: [char; 7] = ['U', 'A', 'F', 'T', 'C', 'P', 'H'];
+    const BOM: &'static [u8] = b"\xef\xbb\xbf";
+    /// Longest header line, with its line ending, that will be searched for.
+    const MAX_LINE: usize = 512;
+    /// Longest binary preamble searched for between the header and the data.
+    const MAX_PREAMBLE: usize = 256;
+    /// Highest `$A` voltage read as whole volts rather than tenths.
+    const WHOLE_VOLTS_LIMIT: i32 = 50;
+
+    fn read_file(file_path: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        Ok(fs::read(Path::new(file_path))?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.file_stream.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.file_stream.is_empty()
+    }
+
+    /// Checks a header line against its checksum, or `None` if the line
+    /// has none, as happens to the last line of some recovered files.
+    fn checksum(header_line: &str) -> Option<bool> {
+        let (value, checksum_s) = header_line.rsplit_once(EdmHeader::END as char)?;
+        let Ok(checksum) = u8::from_str_radix(checksum_s.trim(), 16) else {
+            return Some(false);
+        };
+        let calc_checksum = value.as_bytes()[1..].iter().fold(0, |i, x| i ^ *x);
+        Some(calc_checksum == checksum)
+    }
+    /// Offset of the first header line. A file that has been through a text
+    /// editor can pick up a byte order mark or blank lines in front of it,
+    /// which are skipped.
+    fn header_start(&self) -> usize {
+        let bom = if self.file_stream.starts_with(EdmHeader::BOM) {
+            EdmHeader::BOM.len()
+        } else {
+            0
+        };
+        bom + self.file_stream[bom..]
+            .iter()
+            .take_while(|b| b.is_ascii_whitespace())
+            .count()
+    }
+
+    /// Offset of the newline that ends the header.
+    ///
+    /// Hops from line to line, so only the header is read, and gives up on
+    /// a line longer than any header line could be rather than search a
+    /// whole file of binary for a newline.
+    fn header_end(&self) -> Result<usize, ParseError> {
+        if let Some(header_len) = self.header_len {
+            return Ok(header_len - 1);
+        }
+        let mut start = self.header_start();
+        loop {
+            let rest = &self.file_stream[start..];
+            let end = start
+                + rest[..rest.len().min(EdmHeader::MAX_LINE)]
+                    .iter()
+                    .position(|b| *b == b'\n')
+                    .ok_or(ParseError::HeaderEnd)?;
+            match self.file_stream.get(end + 1) {
+                Some(&EdmHeader::START) => start = end + 1,
+                Some(_) => return Ok(end),
+                None => return Err(ParseError::HeaderEnd),
+            }
+        }
+    }
+
+    fn try_parse(&self) -> Result<Cow<'_, str>, ParseError> {
+        // Header is in ascii for some reason
+        let header = &self.file_stream[self.header_start()..self.header_end()?];
+        match str::from_utf8(header) {
+            Ok(header) => Ok(Cow::Borrowed(header)),
+            // A damaged registration is still worth a best guess, but every
+            // other line has to be read exactly
+            Err(_)
+                if header
+                    .split(|b| *b == b'\n')
+                    .all(|line| line.starts_with(b"$U") || str::from_utf8(line).is_ok()) =>
+            {
+                Ok(String::from_utf8_lossy(header))
+            }
+            Err(_) => Err(ParseError::HeaderEncoding),
+        }
+    }
+
+    /// Splits a header line into its fields, dropping the `$X` tag.
+    ///
+    /// JPI never quotes fields, but free text such as the registration (`$U`)
+    /// could hold a comma. A field wrapped in double quotes is kept whole,
+    /// with `""` inside it standing for a literal quote.
+    fn process_header_clean(header_line: &str) -> Vec<String> {
+        let data = header_line
+            .rsplit_once(EdmHeader::END as char)
+            .map_or(header_line, |(data, _)| data);
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = data.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' if quoted || field.trim().is_empty() => quoted = !quoted,
+                EdmHeader::DELIM if !quoted => fields.push(std::mem::take(&mut field)),
+                _ => field.push(c),
+            }
+        }
+        fields.push(field);
+        fields
+            .into_iter()
+            .skip(1)
+            .map(|v| v.trim().to_string())
+            .collect::<Vec<String>>()
+    }
+
+    fn process_header_rego(header_line: &str) -> Option<String> {
+        let reg = EdmHeader::process_header_clean(header_line);
+        if reg.len() == 1 {
+            Some(reg[0].clone().to_string())
+        } else {
+            None
+        }
+    }
+
+    /// `$H` holds the instrument serial number, or `0` when there is none.
+    fn process_header_serial(header_line: &str) -> Option<String> {
+        let serial = EdmHeader::process_header_clean(header_line)
+            .into_iter()
+            .next()?;
+        if serial.is_empty() || serial.bytes().all(|b| b == b'0') {
+            None
+        } else {
+            Some(serial)
+        }
+    }
+
+    /// Parses every field of a header line as a number, failing unless
+    /// there are at least `expected` of them.
+    fn process_header_numbers<T: str::FromStr>(
+        header_line: &str,
+        line: usize,
+        expected: usize,
+    ) -> Result<Vec<T>, ParseError> {
+        let tag = header_line.chars().nth(1).unwrap_or_default();
+        let fields = EdmHeader::process_header_clean(header_line);
+        if fields.len() < expected {
+            return Err(ParseError::FieldCount {
+                tag,
+                line,
+                expected,
+                found: fields.len(),
+            });
+        }
+        fields
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                v.parse::<T>().map_err(|_| ParseError::InvalidField {
+                    tag,
+                    line,
+                    field: i + 1,
+                    value: v.clone(),
+                })
+            })
+            .collect()
+    }
+
+    fn process_header_alarms(header_line: &str, line: usize) -> Result<Alarms, ParseError> {
+        let alarms = EdmHeader::process_header_numbers::<i32>(header_line, line, 8)?;
+        // Current firmware gives volts in tenths, older firmware in whole
+        // volts. No limit is near 50 V, nor near 5.0 V, so the high limit
+        // tells which one a file uses.
+        let volts_scale = if alarms[0] > EdmHeader::WHOLE_VOLTS_LIMIT {
+            10.0
+        } else {
+            1.0
+        };
+        Ok(Alarms {
+            max_volts: Some(f64::from(alarms[0]) / volts_scale),
+            min_volts: Some(f64::from(alarms[1]) / volts_scale),
+            max_egt_spread: Some(alarms[2]),
+            max_cht: Some(alarms[3]),
+            max_cht_cool_rate: Some(alarms[4]),
+            max_egt: Some(alarms[5]),
+            max_oil_temp: Some(alarms[6]),
+            min_oil_temp: Some(alarms[7]),
+            volts_in_tenths: volts_scale == 10.0,
+        })
+    }
+
+    /// `$T` is month, day, two digit year, hour, minute and an unknown field.
+    /// Years below `year_pivot` are in the 2000s.
+    fn process_header_time(header_line: &str, year_pivot: u32) -> Option<i64> {
+        let time = EdmHeader::process_header_clean(header_line)
+            .iter()
+            .take(5)
+            .map(|v| v.parse::<u32>().ok())
+            .collect::<Option<Vec<u32>>>()?;
+        let [month, day, year, hour, minute] = time[..] else {
+            return None;
+        };
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+        let century = if year < year_pivot { 2000 } else { 1900 };
+        Some(epoch_seconds(
+            century + i64::from(year),
+            month,
+            day,
+            hour,
+            minute,
+            0,
+        ))
+    }
+
+    fn process_header_flight(header_line: &str, line: usize) -> Result<Flight, ParseError> {
+        let tag = header_line.chars().nth(1).unwrap_or_default();
+        let flight = EdmHeader::process_header_numbers::<i64>(header_line, line, 2)?;
+        let invalid = |field: usize| ParseError::InvalidField {
+            tag,
+            line,
+            field: field + 1,
+            value: flight[field].to_string(),
+        };
+        Ok(Flight {
+            number: i32::try_from(flight[0]).map_err(|_| invalid(0))?,
+            words: usize::try_from(flight[1]).map_err(|_| invalid(1))?,
+            ..Default::default()
+        })
+    }
+
+    fn process_header_config(header_line: &str, line: usize) -> Result<Features, ParseError> {
+        let config = EdmHeader::process_header_numbers::<i32>(header_line, line, 3)?;
+        let flags = config[1] as u32 | (config[2] as u32) << 16;
+        // Newer models put a second unknown value and some extra fields
+        // ahead of the firmware version
+        let extended = config.len() > 5;
+        Ok(Features {
+            model: config.first().copied(),
+            firmware_version: config.get(if extended { 6 } else { 4 }).copied(),
+            sensors: Some(Sensors::from_flags(flags)),
+            engine_temperature_unit: None,
+            unknown1: config.get(3).copied(),
+            unknown2: if extended {
+                config.get(4).copied()
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Every line of the header in file order, split into fields but not
+    /// otherwise interpreted.
+    pub fn header_lines(&self) -> Result<Vec<HeaderLine>, ParseError> {
+        Ok(self
+            .try_parse()?
+            .lines()
+            .enumerate()
+            .map(|(index, line)| HeaderLine {
+                number: index + 1,
+                tag: line.chars().nth(1).unwrap_or_default(),
+                fields: EdmHeader::process_header_clean(line),
+                checksum: EdmHeader::checksum(line),
+            })
+            .collect())
+    }
+
+    /// The header lines with `tag`, such as every `$D` flight index line.
+    pub fn lines_with_tag(
+        &self,
+        tag: char,
+    ) -> Result<impl Iterator<Item = HeaderLine>, ParseError> {
+        Ok(self
+            .header_lines()?
+            .into_iter()
+            .filter(move |line| line.tag == tag))
+    }
+
+    /// The registration from the first `$U` line, without reading the rest
+    /// of the header, for picking out one aircraft's files cheaply.
+    pub fn registration_only(&self) -> Result<Option<String>, ParseError> {
+        Ok(self
+            .try_parse()?
+            .lines()
+            .find(|line| line.starts_with("$U"))
+            .and_then(EdmHeader::process_header_rego))
+    }
+
+    /// Parses the header, skipping any line that cannot be read.
+    pub fn data(&self) -> HeaderData {
+        self.read_data(false, &DecodeConfig::default())
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Parses the header, failing on the first line that cannot be read.
+    pub fn try_data(&self) -> Result<HeaderData, ParseError> {
+        self.try_data_with(&DecodeConfig::default())
+    }
+
+    /// Like `try_data`, also failing on a tag `options` does not allow.
+    pub fn try_data_with(&self, options: &DecodeConfig) -> Result<HeaderData, ParseError> {
+        self.read_data(true, options)
+    }
+
+    fn read_data(&self, strict: bool, options: &DecodeConfig) -> Result<HeaderData, ParseError> {
+        let headers = self.try_parse()?;
+        let mut header_data = HeaderData {
+            ..Default::default()
+        };
+        // The first line of each tag that can only appear once
+        let mut seen = BTreeMap::new();
+        for (index, line) in headers.lines().enumerate() {
+            let number = index + 1;
+            let line_text = line.trim().to_string();
+            match EdmHeader::checksum(line) {
+                Some(true) => (),
+                Some(false) => header_data
+                    .diagnostics
+                    .push(Diagnostic::BadHeaderChecksum { line: line_text }),
+                None => header_data
+                    .diagnostics
+                    .push(Diagnostic::MissingHeaderChecksum { line: line_text }),
+            }
+            let tag = line.chars().nth(1).unwrap_or_default();
+            if EdmHeader::SINGLE_TAGS.contains(&tag) {
+                if let Some(first) = seen.get(&tag) {
+                    if options.reject_conflicting_lines && *first != line.trim() {
+                        return Err(ParseError::ConflictingLine { tag, line: number });
+                    }
+                    header_data
+                        .diagnostics
+                        .push(Diagnostic::DuplicateHeaderLine { tag, line: number });
+                    continue;
+                }
+                seen.insert(tag, line.trim());
+            }
+            let result = match tag {
+                'U' => {
+                    header_data.registration = EdmHeader::process_header_rego(line);
+                    Ok(())
+                }
+                'A' => EdmHeader::process_header_alarms(line, number)
+                    .map(|alarms| header_data.alarms = Some(alarms)),
+                'H' => {
+                    header_data.serial_number = EdmHeader::process_header_serial(line);
+                    Ok(())
+                }
+                'P' => EdmHeader::process_header_numbers::<i32>(line, number, 1)
+                    .map(|version| header_data.protocol_version = Some(version[0])),
+                'T' => {
+                    let pivot = options
+                        .year_pivot
+                        .unwrap_or(DecodeConfig::DEFAULT_YEAR_PIVOT);
+                    header_data.download_time = EdmHeader::process_header_time(line, pivot);
+                    Ok(())
+                }
+                'C' => EdmHeader::process_header_config(line, number)
+                    .map(|features| header_data.features = Some(features)),
+                'D' => EdmHeader::process_header_flight(line, number)
+                    .map(|flight| header_data.flights.push(flight)),
+                // Fuel setup and the trailing line are known but not used
+                'F' | 'L' => Ok(()),
+                tag if options.strict_tags => {
+                    return Err(ParseError::UnknownTag { tag, line: number })
+                }
+                _ => Ok(()),
+            };
+            if strict {
+                result?;
+            }
+        }
+        Ok(header_data)
+    }
+
+    /// Decodes every flight listed in the header.
+    ///
+    /// Fails with `ParseError::MissingConfig` if the header has no `$C` line.
+    pub fn flights(&self) -> Result<Vec<Flight>, ParseError> {
+        self.flights_with(&DecodeConfig::default())
+    }
+
+    /// Decodes the flights that started at or after `start` and before
+    /// `end`, both in seconds since the Unix epoch. Flights with no start
+    /// time are left out.
+    pub fn flights_between(&self, start: i64, end: i64) -> Result<Vec<Flight>, ParseError> {
+        let mut flights = self.flights()?;
+        flights.retain(|flight| {
+            flight
+                .header
+                .as_ref()
+                .and_then(|h| h.start_time)
+                .is_some_and(|t| (start..end).contains(&t))
+        });
+        Ok(flights)
+    }
+
+    /// Where the first flight block starts. That is normally straight after
+    /// the header, but some firmware puts a short binary preamble first, so
+    /// the first `MAX_PREAMBLE` bytes are searched for a valid flight header
+    /// with the first flight's number. Without one the block is taken to
+    /// start after the header and decodes with diagnostics as usual.
+    fn data_start(&self, first: Option<&Flight>, long_header: bool) -> Result<usize, ParseError> {
+        let start = self.header_end()? + 1;
+        let Some(first) = first else {
+            return Ok(start);
+        };
+        let found = (start..start + EdmHeader::MAX_PREAMBLE).find(|offset| {
+            self.file_stream
+                .get(*offset..)
+                .and_then(|block| FlightHeader::parse(block, long_header))
+                .is_some_and(|(header, _)| header.number == first.number)
+        });
+        Ok(found.unwrap_or(start))
+    }
+
+    /// Reads every flight's header and counts its records without decoding
+    /// them, for listing a file quickly. `Flight::sample_count` and
+    /// `Flight::duration` agree with a full decode; `records` is left empty.
+    pub fn skim_flights(&self) -> Result<Vec<Flight>, ParseError> {
+        Ok(self.skim()?.0)
+    }
+
+    /// `skim_flights`, with whether the flights have the long header.
+    fn skim(&self) -> Result<(Vec<Flight>, bool), ParseError> {
+        let header_data = self.read_data(false, &DecodeConfig::default())?;
+        let features = header_data
+            .features
+            .as_ref()
+            .ok_or(ParseError::MissingConfig)?;
+        let long_header = features.long_flight_header();
+
+        let mut flights = header_data.flights;
+        let mut offset = self.data_start(flights.first(), long_header)?;
+        for flight in &mut flights {
+            flight.offset = offset;
+            let block = self.file_stream.get(offset..).unwrap_or_default();
+            offset += flight.skim(block, long_header);
+        }
+        Ok((flights, long_header))
+    }
+
+    /// The first record of every flight with its flight number, for a
+    /// preview of each flight's starting conditions. Blocks are found as
+    /// `skim_flights` finds them, framing records without turning them into
+    /// `Record`s. A flight whose first record cannot be read is left out.
+    ///
+    /// Fails with `ParseError::MissingConfig` if the header has no `$C` line.
+    pub fn first_records(&self) -> Result<Vec<(i32, Record)>, ParseError> {
+        let (flights, long_header) = self.skim()?;
+        Ok(flights
+            .iter()
+            .filter_map(|flight| {
+                let block = self.file_stream.get(flight.offset..).unwrap_or_default();
+                let end = (flight.words * 2).min(block.len());
+                let record = Flight::first_record(&block[..end], long_header)?;
+                Some((flight.number, record))
+            })
+            .collect())
+    }
+
+    /// Decodes every flight listed in the header using `options`.
+    pub fn flights_with(&self, options: &DecodeConfig) -> Result<Vec<Flight>, ParseError> {
+        let header_data = self.read_data(false, options)?;
+        let features = header_data
+            .features
+            .as_ref()
+            .ok_or(ParseError::MissingConfig)?;
+        let config = features.sensors.as_ref();
+        let long_header = features.long_flight_header();
+
+        // Flight blocks follow the header back to back
+        let mut flights = header_data.flights;
+        let mut offset = self.data_start(flights.first(), long_header)?;
+        for flight in &mut flights {
+            flight.offset = offset;
+            let block = self.file_stream.get(offset..).unwrap_or_default();
+            offset += flight.decode(block, config, long_header, options)?;
+        }
+        Ok(match options.merge_within_secs {
+            Some(secs) => merge_flights(flights, secs),
+            None => flights,
+        })
+    }
+}
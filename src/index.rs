@@ -0,0 +1,172 @@
+use std::{
+    error::Error,
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{error::ParseError, header::EdmHeader};
+
+/// Where each flight in a file starts, cached in a small `.jpi.idx` sidecar
+/// so a large archive can be browsed without decoding it again.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SidecarIndex {
+    pub registration: Option<String>,
+    /// Length of the file the index was built from, to catch a file that
+    /// was replaced without its modification time moving on.
+    pub source_len: u64,
+    pub flights: Vec<IndexEntry>,
+}
+
+/// One flight in a `SidecarIndex`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub number: i32,
+    /// Byte offset of the flight's data block in the file.
+    pub offset: u64,
+    /// Length of the flight's data block in 16-bit words.
+    pub words: u64,
+    /// Start of the flight in seconds since the Unix epoch.
+    pub start_time: Option<i64>,
+}
+
+impl SidecarIndex {
+    /// Sidecar format marker and version.
+    const MAGIC: &'static [u8; 8] = b"JPIIDX\x00\x01";
+
+    /// Indexes a file with `EdmHeader::skim_flights`, which reads each
+    /// flight's header without decoding its records.
+    pub fn from_file(header: &EdmHeader) -> Result<SidecarIndex, ParseError> {
+        let flights = header.skim_flights()?;
+        Ok(SidecarIndex {
+            registration: header.try_data()?.registration,
+            source_len: header.len() as u64,
+            flights: flights
+                .iter()
+                .map(|flight| IndexEntry {
+                    number: flight.number,
+                    offset: flight.offset as u64,
+                    words: flight.words as u64,
+                    start_time: flight.header.as_ref().and_then(|h| h.start_time),
+                })
+                .collect(),
+        })
+    }
+
+    /// The sidecar path for `source`, which is `source` with `.idx` added.
+    pub fn path_for<P: AsRef<Path>>(source: P) -> PathBuf {
+        let mut path = source.as_ref().as_os_str().to_owned();
+        path.push(".idx");
+        PathBuf::from(path)
+    }
+
+    /// Loads the sidecar for `source` if it exists and is at least as new
+    /// as `source`. A sidecar that is stale or cannot be read gives `None`.
+    pub fn load<P: AsRef<Path>>(source: P) -> Option<SidecarIndex> {
+        let source = source.as_ref();
+        let sidecar = SidecarIndex::path_for(source);
+        let source_meta = fs::metadata(source).ok()?;
+        if fs::metadata(&sidecar).ok()?.modified().ok()? < source_meta.modified().ok()? {
+            return None;
+        }
+        let index = SidecarIndex::read(fs::File::open(sidecar).ok()?).ok()?;
+        (index.source_len == source_meta.len()).then_some(index)
+    }
+
+    /// Writes the sidecar for `source`.
+    pub fn save<P: AsRef<Path>>(&self, source: P) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes)?;
+        fs::write(SidecarIndex::path_for(source), bytes)
+    }
+
+    /// Loads the sidecar for `source`, or indexes `source` and saves one
+    /// when there is no usable sidecar.
+    pub fn open<P: AsRef<Path>>(source: P) -> Result<SidecarIndex, Box<dyn Error>> {
+        let source = source.as_ref();
+        if let Some(index) = SidecarIndex::load(source) {
+            return Ok(index);
+        }
+        let header = EdmHeader::from_bytes(fs::read(source)?);
+        let index = SidecarIndex::from_file(&header)?;
+        index.save(source)?;
+        Ok(index)
+    }
+
+    /// Writes the index in the sidecar format. Numbers are little endian.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(SidecarIndex::MAGIC)?;
+        writer.write_all(&self.source_len.to_le_bytes())?;
+        match &self.registration {
+            Some(registration) => {
+                let len = u16::try_from(registration.len())
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "registration"))?;
+                writer.write_all(&[1])?;
+                writer.write_all(&len.to_le_bytes())?;
+                writer.write_all(registration.as_bytes())?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+        writer.write_all(&(self.flights.len() as u32).to_le_bytes())?;
+        for flight in &self.flights {
+            writer.write_all(&flight.number.to_le_bytes())?;
+            writer.write_all(&flight.offset.to_le_bytes())?;
+            writer.write_all(&flight.words.to_le_bytes())?;
+            match flight.start_time {
+                Some(start) => {
+                    writer.write_all(&[1])?;
+                    writer.write_all(&start.to_le_bytes())?;
+                }
+                None => writer.write_all(&[0])?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads an index written by `write`.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<SidecarIndex> {
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != SidecarIndex::MAGIC {
+            return Err(invalid("not a jpi index"));
+        }
+        let source_len = u64::from_le_bytes(read_array(&mut reader)?);
+        let registration = match read_array::<1>(&mut reader)? {
+            [0] => None,
+            [1] => {
+                let len = u16::from_le_bytes(read_array(&mut reader)?);
+                let mut bytes = vec![0; len as usize];
+                reader.read_exact(&mut bytes)?;
+                Some(String::from_utf8(bytes).map_err(|_| invalid("registration"))?)
+            }
+            _ => return Err(invalid("registration")),
+        };
+        let count = u32::from_le_bytes(read_array(&mut reader)?);
+        let flights = (0..count)
+            .map(|_| {
+                Ok(IndexEntry {
+                    number: i32::from_le_bytes(read_array(&mut reader)?),
+                    offset: u64::from_le_bytes(read_array(&mut reader)?),
+                    words: u64::from_le_bytes(read_array(&mut reader)?),
+                    start_time: match read_array::<1>(&mut reader)? {
+                        [0] => None,
+                        [1] => Some(i64::from_le_bytes(read_array(&mut reader)?)),
+                        _ => return Err(invalid("start time")),
+                    },
+                })
+            })
+            .collect::<io::Result<Vec<IndexEntry>>>()?;
+        Ok(SidecarIndex {
+            registration,
+            source_len,
+            flights,
+        })
+    }
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
@@ -0,0 +1,469 @@
+use std::{
+    fmt,
+    io::{self, Write},
+};
+
+use crate::{
+    channel::Channel,
+    error::ParseError,
+    export::ExportOptions,
+    flight::{Flight, Record},
+    header::{EdmHeader, HeaderData},
+    stats::FlightStats,
+};
+
+/// A JSON document, written compact or indented.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    /// Written without a fraction when it is a whole number. NaN and
+    /// infinities have no JSON form and are written as `null`.
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Members in the order they were added.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// The member `key` of an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Writes the document on one line, or indented by two spaces a level
+    /// if `pretty` is set.
+    pub fn write<W: Write>(&self, mut writer: W, pretty: bool) -> io::Result<()> {
+        let text = if pretty {
+            let mut text = String::new();
+            self.write_pretty(&mut text, 0);
+            text
+        } else {
+            self.to_string()
+        };
+        writer.write_all(text.as_bytes())
+    }
+
+    fn write_pretty(&self, out: &mut String, depth: usize) {
+        let indent = |depth: usize| "  ".repeat(depth);
+        match self {
+            Json::Array(items) if !items.is_empty() => {
+                out.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(&indent(depth + 1));
+                    item.write_pretty(out, depth + 1);
+                    out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&indent(depth));
+                out.push(']');
+            }
+            Json::Object(members) if !members.is_empty() => {
+                out.push_str("{\n");
+                for (i, (key, value)) in members.iter().enumerate() {
+                    out.push_str(&indent(depth + 1));
+                    out.push_str(&format!("{}: ", Json::String(key.clone())));
+                    value.write_pretty(out, depth + 1);
+                    out.push_str(if i + 1 < members.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&indent(depth));
+                out.push('}');
+            }
+            _ => out.push_str(&self.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    /// The compact form.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Number(n) if !n.is_finite() => write!(f, "null"),
+            Json::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Json::Number(n) => write!(f, "{n}"),
+            Json::String(s) => {
+                write!(f, "\"")?;
+                for c in s.chars() {
+                    match c {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        '\r' => write!(f, "\\r")?,
+                        '\t' => write!(f, "\\t")?,
+                        c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                        c => write!(f, "{c}")?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}:{value}", Json::String(key.clone()))?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Json {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(value: f64) -> Json {
+        Json::Number(value)
+    }
+}
+
+impl From<i32> for Json {
+    fn from(value: i32) -> Json {
+        Json::Number(f64::from(value))
+    }
+}
+
+impl From<i64> for Json {
+    fn from(value: i64) -> Json {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Json {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Json {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Json {
+        Json::String(value)
+    }
+}
+
+/// Draft of JSON Schema the `json_schema` functions follow.
+const SCHEMA_DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+fn object(members: Vec<(&str, Json)>) -> Json {
+    Json::Object(
+        members
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect(),
+    )
+}
+
+fn of_type(name: &str) -> Json {
+    object(vec![("type", name.into())])
+}
+
+fn nullable(name: &str) -> Json {
+    object(vec![(
+        "type",
+        Json::Array(vec![name.into(), "null".into()]),
+    )])
+}
+
+fn array_of(items: Json) -> Json {
+    object(vec![("type", "array".into()), ("items", items)])
+}
+
+/// An object schema with exactly `properties`, every one of them required.
+fn closed_object(properties: Vec<(&str, Json)>) -> Json {
+    let required = properties.iter().map(|(name, _)| (*name).into()).collect();
+    object(vec![
+        ("type", "object".into()),
+        ("properties", object(properties)),
+        ("required", Json::Array(required)),
+        ("additionalProperties", Json::Bool(false)),
+    ])
+}
+
+/// `schema` as a document of its own, with the draft and a title.
+fn titled(title: &str, schema: Json) -> Json {
+    let Json::Object(members) = schema else {
+        return schema;
+    };
+    let mut document = vec![
+        ("$schema".to_string(), SCHEMA_DRAFT.into()),
+        ("title".to_string(), title.into()),
+    ];
+    document.extend(members);
+    Json::Object(document)
+}
+
+impl Flight {
+    /// A JSON Schema (draft 2020-12) for the document `to_json` writes.
+    /// Channel members are not listed by name, since they depend on what
+    /// the flight recorded; any member of a record other than `time` and
+    /// `mark` is a reading or `null`.
+    pub fn json_schema() -> Json {
+        let record = object(vec![
+            ("type", "object".into()),
+            (
+                "properties",
+                object(vec![
+                    ("time", nullable("integer")),
+                    ("mark", of_type("integer")),
+                ]),
+            ),
+            ("required", Json::Array(vec!["time".into(), "mark".into()])),
+            ("additionalProperties", nullable("number")),
+        ]);
+        titled(
+            "jpi flight",
+            closed_object(vec![
+                ("number", of_type("integer")),
+                ("start_time", nullable("integer")),
+                ("interval_secs", nullable("integer")),
+                ("diagnostics", array_of(of_type("string"))),
+                ("records", array_of(record)),
+            ]),
+        )
+    }
+
+    /// The flight as JSON: its number, start time and interval, any
+    /// diagnostics, and one object per record. A record holds `time` in
+    /// seconds since the Unix epoch, each recorded channel under its
+    /// `Channel::label`, and the raw `mark`.
+    pub fn to_json(&self) -> Json {
+        self.to_json_with(&ExportOptions::default())
+    }
+
+    /// Like `to_json`, with the two `options` that apply to JSON: channels
+    /// in `aliases` are named by their alias, and with `si_units` readings
+    /// are converted by `Channel::to_si`, as they are for CSV. The other
+    /// options only shape CSV rows and are ignored.
+    pub fn to_json_with(&self, options: &ExportOptions) -> Json {
+        let label = |channel: &Channel| {
+            options
+                .aliases
+                .get(channel)
+                .cloned()
+                .unwrap_or_else(|| channel.label())
+        };
+        let value = |record: &Record, channel: Channel| {
+            let value = record.get(channel)?;
+            Some(match options.si_units {
+                true => channel.to_si(value).unwrap_or(value),
+                false => value,
+            })
+        };
+        let channels = self
+            .header
+            .as_ref()
+            .map(|h| h.sensors.present())
+            .unwrap_or_default();
+        let records = self
+            .records
+            .iter()
+            .map(|record| {
+                let mut members = vec![("time".to_string(), record.timestamp.into())];
+                members.extend(
+                    channels
+                        .iter()
+                        .map(|channel| (label(channel), value(record, *channel).into())),
+                );
+                members.push(("mark".to_string(), record.mark.into()));
+                Json::Object(members)
+            })
+            .collect();
+        let header = self.header.as_ref();
+        Json::Object(vec![
+            ("number".to_string(), self.number.into()),
+            (
+                "start_time".to_string(),
+                header.and_then(|h| h.start_time).into(),
+            ),
+            (
+                "interval_secs".to_string(),
+                header.map(|h| h.interval_secs).into(),
+            ),
+            (
+                "diagnostics".to_string(),
+                Json::Array(
+                    self.diagnostics
+                        .iter()
+                        .map(|d| d.to_string().into())
+                        .collect(),
+                ),
+            ),
+            ("records".to_string(), Json::Array(records)),
+        ])
+    }
+}
+
+impl HeaderData {
+    /// A JSON Schema (draft 2020-12) for the document `to_json` writes.
+    pub fn json_schema() -> Json {
+        titled("jpi header", HeaderData::schema())
+    }
+
+    fn schema() -> Json {
+        closed_object(vec![
+            ("registration", nullable("string")),
+            ("serial_number", nullable("string")),
+            ("model", nullable("string")),
+            ("firmware_version", nullable("integer")),
+            ("download_time", nullable("integer")),
+            ("protocol_version", nullable("integer")),
+            ("flight_count", of_type("integer")),
+        ])
+    }
+
+    /// The header as JSON: the registration and serial number, the model
+    /// and firmware from `$C`, the download time in seconds since the Unix
+    /// epoch, the protocol version and how many flights are listed.
+    pub fn to_json(&self) -> Json {
+        let features = self.features.as_ref();
+        Json::Object(vec![
+            ("registration".to_string(), self.registration.clone().into()),
+            (
+                "serial_number".to_string(),
+                self.serial_number.clone().into(),
+            ),
+            (
+                "model".to_string(),
+                features
+                    .and_then(|f| f.model_name())
+                    .map(|name| name.into_owned())
+                    .into(),
+            ),
+            (
+                "firmware_version".to_string(),
+                features.and_then(|f| f.firmware_version).into(),
+            ),
+            ("download_time".to_string(), self.download_time.into()),
+            ("protocol_version".to_string(), self.protocol_version.into()),
+            ("flight_count".to_string(), self.flights.len().into()),
+        ])
+    }
+}
+
+impl FlightStats {
+    /// A JSON Schema (draft 2020-12) for the document `to_json` writes.
+    /// `max_cht` is a number rather than an integer, since it is not whole
+    /// once converted to SI.
+    pub fn json_schema() -> Json {
+        titled("jpi flight summary", FlightStats::schema())
+    }
+
+    fn schema() -> Json {
+        closed_object(vec![
+            ("number", of_type("integer")),
+            ("records", of_type("integer")),
+            ("start_time", nullable("integer")),
+            ("duration_secs", of_type("integer")),
+            ("max_cht", nullable("number")),
+            ("fuel_used", nullable("number")),
+            ("faulted_channels", array_of(of_type("string"))),
+        ])
+    }
+
+    /// The summary as JSON, with each member named as its field and the
+    /// faulted channels by `Channel::label`.
+    pub fn to_json(&self) -> Json {
+        self.to_json_with(false)
+    }
+
+    /// Like `to_json`, with `max_cht` and `fuel_used` converted by
+    /// `Channel::to_si` if `si_units` is set.
+    pub fn to_json_with(&self, si_units: bool) -> Json {
+        let convert = |channel: Channel, value: f64| match si_units {
+            true => channel.to_si(value).unwrap_or(value),
+            false => value,
+        };
+        Json::Object(vec![
+            ("number".to_string(), self.number.into()),
+            ("records".to_string(), self.records.into()),
+            ("start_time".to_string(), self.start_time.into()),
+            ("duration_secs".to_string(), self.duration_secs.into()),
+            (
+                "max_cht".to_string(),
+                self.max_cht
+                    .map(|cht| convert(Channel::Cht(1), f64::from(cht)))
+                    .into(),
+            ),
+            (
+                "fuel_used".to_string(),
+                self.fuel_used
+                    .map(|fuel| convert(Channel::FuelUsed, fuel))
+                    .into(),
+            ),
+            (
+                "faulted_channels".to_string(),
+                Json::Array(
+                    self.faulted_channels
+                        .iter()
+                        .map(|c| c.label().into())
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+}
+
+impl EdmHeader {
+    /// A JSON Schema (draft 2020-12) for the document `summary_json`
+    /// writes, built from `HeaderData::json_schema` and
+    /// `FlightStats::json_schema`.
+    pub fn summary_json_schema() -> Json {
+        titled(
+            "jpi file summary",
+            closed_object(vec![
+                ("header", HeaderData::schema()),
+                ("flights", array_of(FlightStats::schema())),
+            ]),
+        )
+    }
+
+    /// The whole file in one document: `header` from `HeaderData::to_json`
+    /// and `flights`, one `FlightStats::to_json` per flight.
+    pub fn summary_json(&self) -> Result<Json, ParseError> {
+        self.summary_json_with(false)
+    }
+
+    /// Like `summary_json`, with each flight's summary from
+    /// `FlightStats::to_json_with`.
+    pub fn summary_json_with(&self, si_units: bool) -> Result<Json, ParseError> {
+        let flights = self.flights()?;
+        Ok(Json::Object(vec![
+            ("header".to_string(), self.try_data()?.to_json()),
+            (
+                "flights".to_string(),
+                Json::Array(
+                    flights
+                        .iter()
+                        .map(|f| f.stats().to_json_with(si_units))
+                        .collect(),
+                ),
+            ),
+        ]))
+    }
+}
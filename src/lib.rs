@@ -0,0 +1,30 @@
+mod analysis;
+mod channel;
+mod config;
+mod diagnostic;
+mod encode;
+mod error;
+mod export;
+mod flight;
+mod header;
+mod index;
+mod json;
+pub mod prelude;
+mod stats;
+mod time;
+mod walk;
+
+pub use analysis::*;
+pub use channel::*;
+pub use config::*;
+pub use diagnostic::*;
+pub use encode::*;
+pub use error::*;
+pub use export::*;
+pub use flight::*;
+pub use header::*;
+pub use index::*;
+pub use json::*;
+pub use stats::*;
+pub use time::format_timestamp;
+pub use walk::*;
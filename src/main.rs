@@ -1,241 +1,355 @@
-use std::{default, fs, path::Path, str};
+use std::{collections::HashMap, io::Write, process::ExitCode};
 
-#[derive(Debug, Default)]
-struct Alarms {
-    min_volts: Option<f64>,
-    max_volts: Option<f64>,
-    max_egt_spread: Option<i32>,
-    max_cht: Option<i32>,
-    max_cht_cool_rate: Option<i32>,
-    max_egt: Option<i32>,
-    min_oil_temp: Option<i32>,
-    max_oil_temp: Option<i32>,
-}
-#[derive(Debug)]
+use jpi::{
+    check_aliases, format_timestamp, walk_dir, walk_dir_for_tail, write_flights_csv, Alarms,
+    Channel, CsvDialect, EdmHeader, ExportOptions, Flight, HeaderData, ParseError,
+};
 
-enum FuelFlowUnits {
-    GPH,
-    PPH,
-    LPH,
-    KPH,
-}
-#[derive(Debug)]
-enum TempUnits {
-    Farenheit,
-    Celcius,
-}
+const USAGE: &str = "\
+Usage: jpi [OPTIONS] [FILE.JPI | DIRECTORY]
 
-#[derive(Debug, Default)]
-struct Fuel {
-    fuel_flow_units: Option<FuelFlowUnits>,
-    full_level: Option<i32>,
-    warning_level: Option<i32>,
-    k_factor_ff1: Option<i32>,
-    k_factor_ff2: Option<i32>,
-}
+With no output option, lists the flights in FILE.JPI (./FILE.JPI by
+default), or with a directory the files under it.
 
-#[derive(Debug, Default)]
-struct Sensors {
-    egt_count: Option<i32>,
-    cht_count: Option<i32>,
-    volts: bool,
-    oil_temp: bool,
-    tit1: bool,
-    tit2: bool,
-    oat: bool,
-    fuel_flow: bool,
-    iat: bool,
-    cdt: bool,
-    map: bool,
-    rpm: bool,
-}
-
-#[derive(Debug, Default)]
-struct Features {
-    model: Option<i32>,
-    firmware_version: Option<i32>,
-    sensors: Option<Sensors>,
-    engine_temperature_unit: Option<TempUnits>,
-    unknown1: Option<i32>,
-    unknown2: Option<i32>,
-}
-
-#[derive(Debug, Default)]
-struct HeaderData {
-    registration: Option<String>,
-    alarms: Option<Alarms>,
-    fuel: Option<Fuel>,
-    download_time: Option<i64>,
-    protocol_version: Option<i32>,
-    features: Option<Features>,
-}
+  --list                 list the flights, the default
+  --version-info         describe the instrument and what is decoded
+  --csv NUMBER|all       write one flight, or all of them, as CSV
+  --json NUMBER          write one flight as JSON
+  --summary              write the whole file's summary as JSON
+  --json-schema          write the JSON schema, of --summary if given
+  --self-test NUMBER     re-encode a flight and compare it
+  --dialect native|eztrends
+  --columns EGT,CHT,...  CSV columns to write
+  --downsample SECONDS   merge CSV records into buckets
+  --elapsed              CSV time as seconds since the first record
+  --si                   SI units
+  --raw                  CSV values as the stored integers
+  --dedupe               write runs of equal CSV rows once
+  --fill-gaps            lay CSV records on a regular grid
+  --alias LABEL=NAME     rename a CSV column
+  --redline NAME=VALUE   override an alarm limit, such as CHT=420
+  --pretty               indent JSON
+  --tail REGISTRATION    only scan files for this aircraft
+  --help                 show this message
+";
 
-struct EdmHeader {
-    parsed: bool,
-    file_name: &'static str,
-    file_stream: Vec<u8>,
-    data: Option<HeaderData>,
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
 }
 
-impl EdmHeader {
-    fn new(file_path: &'static str) -> Result<EdmHeader, Box<dyn std::error::Error>> {
-        Ok(EdmHeader {
-            file_name: file_path,
-            file_stream: EdmHeader::read_file(file_path)?,
-            parsed: false,
-            data: None,
-        })
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut version_info = false;
+    let mut self_test = None;
+    let mut csv = None;
+    let mut dialect = CsvDialect::Native;
+    let mut columns = None;
+    let mut downsample_secs = None;
+    let mut elapsed_time = false;
+    let mut si_units = false;
+    let mut dedupe = false;
+    let mut raw_values = false;
+    let mut fill_gaps = false;
+    let mut json = None;
+    let mut pretty = false;
+    let mut summary = false;
+    let mut json_schema = false;
+    let mut aliases = HashMap::new();
+    let mut redlines = Vec::new();
+    let mut tail = None;
+    let mut file_path = "./FILE.JPI".to_string();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // Listing is what runs when no other output is asked for
+            "--list" => (),
+            "--version-info" => version_info = true,
+            "--elapsed" => elapsed_time = true,
+            "--si" => si_units = true,
+            "--dedupe" => dedupe = true,
+            "--raw" => raw_values = true,
+            "--fill-gaps" => fill_gaps = true,
+            "--pretty" => pretty = true,
+            "--summary" => summary = true,
+            "--json-schema" => json_schema = true,
+            "--json" => {
+                json = Some(
+                    args.next()
+                        .ok_or("--json needs a flight number")?
+                        .parse::<i32>()?,
+                )
+            }
+            "--self-test" => {
+                self_test = Some(
+                    args.next()
+                        .ok_or("--self-test needs a flight number")?
+                        .parse::<i32>()?,
+                )
+            }
+            "--redline" => {
+                let redline = args
+                    .next()
+                    .ok_or("--redline needs a NAME=VALUE such as CHT=420")?;
+                let (name, value) = redline
+                    .split_once('=')
+                    .ok_or(format!("--redline {redline} is not NAME=VALUE"))?;
+                redlines.push((name.to_string(), value.parse::<f64>()?));
+            }
+            "--csv" => {
+                // `all` writes every flight to one CSV
+                let flight = args.next().ok_or("--csv needs a flight number or all")?;
+                csv = match flight.as_str() {
+                    "all" => Some(None),
+                    number => Some(Some(number.parse::<i32>()?)),
+                }
+            }
+            "--columns" => {
+                columns = Some(
+                    args.next()
+                        .ok_or("--columns needs a list such as EGT,CHT,FF")?
+                        .split(',')
+                        .map(str::to_string)
+                        .collect::<Vec<String>>(),
+                )
+            }
+            "--downsample" => {
+                downsample_secs = Some(
+                    args.next()
+                        .ok_or("--downsample needs a number of seconds")?
+                        .parse::<i64>()?,
+                )
+            }
+            "--alias" => {
+                let alias = args
+                    .next()
+                    .ok_or("--alias needs a name such as EGT1=Front")?;
+                let (label, name) = alias
+                    .split_once('=')
+                    .ok_or("--alias needs a name such as EGT1=Front")?;
+                let channel =
+                    Channel::from_label(label).ok_or(format!("unknown channel {label:?}"))?;
+                aliases.insert(channel, name.to_string());
+            }
+            "--tail" => {
+                tail = Some(
+                    args.next()
+                        .ok_or("--tail needs a registration such as N123AB")?,
+                )
+            }
+            "--dialect" => {
+                dialect = match args.next().as_deref() {
+                    Some("native") => CsvDialect::Native,
+                    Some("eztrends") => CsvDialect::EzTrends,
+                    _ => return Err("--dialect must be native or eztrends".into()),
+                }
+            }
+            "--help" | "-h" => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            option if option.starts_with('-') => {
+                return Err(format!("unknown option {option}\n\n{USAGE}").into())
+            }
+            _ => file_path = arg,
+        }
     }
 
-    const START: u8 = b'$';
-    const END: u8 = b'*';
-    const DELIM: char = ',';
-
-    fn read_file(file_path: &'static str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        Ok(fs::read(Path::new(file_path))?)
+    if json_schema {
+        // With --summary the schema is of the summary document instead
+        let schema = if summary {
+            EdmHeader::summary_json_schema()
+        } else {
+            Flight::json_schema()
+        };
+        let mut stdout = std::io::stdout().lock();
+        schema.write(&mut stdout, true)?;
+        writeln!(stdout)?;
+        return Ok(());
     }
 
-    fn len(&self) -> usize {
-        self.file_stream.len()
+    if std::path::Path::new(&file_path).is_dir() {
+        let found: Box<dyn Iterator<Item = _>> = match &tail {
+            Some(tail) => Box::new(walk_dir_for_tail(&file_path, tail)),
+            None => Box::new(walk_dir(&file_path)),
+        };
+        print_scan(found);
+        return Ok(());
     }
 
-    fn checksum(header_line: &str) -> bool {
-        let (value, checksum_s) = header_line
-            .split_once(EdmHeader::END as char)
-            .expect("Checksum not found");
-        let checksum = u8::from_str_radix(checksum_s, 16).expect("Could not read checksum");
-        let calc_checksum = value.as_bytes()[1..].iter().fold(0, |i, x| i ^ *x);
-        calc_checksum == checksum
+    let header: EdmHeader = EdmHeader::new(&file_path)?;
+    let data = header.try_data()?;
+    let mut alarms = data.alarms.clone().unwrap_or_default();
+    for (name, value) in &redlines {
+        alarms.set_redline(name, *value)?;
     }
-    fn parse(&self) -> &str {
-        // Find where the header ends
-        let header_term = self
-            .file_stream
-            .windows(2)
-            .enumerate()
-            .find(|(_, v)| v[0] == b'\n' && v[1] != EdmHeader::START)
-            .map(|(i, _)| i)
-            .expect("Could not find end of header!");
-
-        // Header is in ascii for some reason
-        let header = str::from_utf8(&self.file_stream[..header_term])
-            .expect("Could not parse header as utf8");
-
-        // Validate header checksums
-        header
-            .lines()
-            .map(EdmHeader::checksum)
-            .collect::<Vec<bool>>();
 
+    if let Some(flight) = self_test {
+        let result = header
+            .self_test(flight)?
+            .ok_or(format!("No flight {flight} in {file_path}"))?;
+        println!("{result:#?}");
+        if !result.values_match {
+            return Err("Re-encoded flight does not decode to the same values".into());
+        }
+    } else if let Some(number) = csv {
+        let mut flights = header.flights()?;
+        if let Some(number) = number {
+            flights.retain(|f| f.number == number);
+            if flights.is_empty() {
+                return Err(format!("No flight {number} in {file_path}").into());
+            }
+        }
+        let sensors = match number {
+            Some(_) => flights[0].header.as_ref().map(|h| h.sensors.clone()),
+            None => data.features.as_ref().and_then(|f| f.sensors.clone()),
+        };
+        let channels = match (&columns, &sensors) {
+            (Some(columns), Some(sensors)) => Some(sensors.select_channels(columns)?),
+            _ => None,
+        };
+        let options = ExportOptions {
+            dialect,
+            channels,
+            downsample_secs,
+            elapsed_time,
+            si_units,
+            raw_values,
+            dedupe,
+            fill_gaps,
+            aliases,
+            // As decoded by `flights`
+            ..Default::default()
+        };
+        match number {
+            Some(_) => flights[0].write_csv_with(std::io::stdout().lock(), &options)?,
+            None => write_flights_csv(&flights, std::io::stdout().lock(), &options)?,
+        }
+    } else if let Some(number) = json {
+        let flight = header
+            .flights()?
+            .into_iter()
+            .find(|f| f.number == number)
+            .ok_or(format!("No flight {number} in {file_path}"))?;
+        let mut stdout = std::io::stdout().lock();
+        let options = ExportOptions {
+            si_units,
+            aliases,
+            ..Default::default()
+        };
+        check_aliases(&options.aliases)?;
+        flight.to_json_with(&options).write(&mut stdout, pretty)?;
+        writeln!(stdout)?;
+    } else if summary {
+        let mut stdout = std::io::stdout().lock();
         header
+            .summary_json_with(si_units)?
+            .write(&mut stdout, pretty)?;
+        writeln!(stdout)?;
+    } else if version_info {
+        print_version_info(&data);
+    } else {
+        print_list(&header, &data, &alarms, si_units)?;
     }
+    Ok(())
+}
 
-    fn process_header_clean(header_line: &str) -> Vec<String> {
-        let (data, _) = header_line
-            .split_once(EdmHeader::END as char)
-            .expect("Checksum not found");
-        data.split(EdmHeader::DELIM)
-            .enumerate()
-            .filter_map(|(i, v)| {
-                if i != 0 {
-                    Some(v.trim().to_string())
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<String>>()
-    }
-
-    fn process_header_rego(header_line: &str) -> Option<String> {
-        let reg = EdmHeader::process_header_clean(header_line);
-        if reg.len() == 1 {
-            Some(reg[0].clone().to_string())
-        } else {
-            None
+/// Prints one row per file found in a directory scan: its path, then its
+/// registration and flight count or why its header could not be read.
+fn print_scan(found: impl Iterator<Item = (std::path::PathBuf, Result<HeaderData, ParseError>)>) {
+    for (path, header) in found {
+        match header {
+            Ok(data) => println!(
+                "{}  {}  {} flights",
+                path.display(),
+                data.registration.unwrap_or_default(),
+                data.flights.len()
+            ),
+            Err(e) => println!("{}  error: {e}", path.display()),
         }
     }
+}
 
-    fn process_header_alarms(header_line: &str) -> Option<Alarms> {
-        let mut alarms = EdmHeader::process_header_clean(header_line);
-        let mut alarms_itr = alarms.iter_mut();
-        Some(Alarms {
-            max_volts: Some(
-                alarms_itr
-                    .next()?
-                    .parse::<f64>()
-                    .expect("Could not parse alarm")
-                    / 10.0,
-            ),
-            min_volts: Some(
-                &alarms_itr
-                    .next()?
-                    .parse::<f64>()
-                    .expect("Could not parse alarm")
-                    / 10.0,
-            ),
-            max_egt_spread: Some(
-                alarms_itr
-                    .next()?
-                    .parse::<i32>()
-                    .expect("Could not parse alarm"),
-            ),
-            max_cht: Some(
-                alarms_itr
-                    .next()?
-                    .parse::<i32>()
-                    .expect("Could not parse alarm"),
-            ),
-            max_cht_cool_rate: Some(
-                alarms_itr
-                    .next()?
-                    .parse::<i32>()
-                    .expect("Could not parse alarm"),
+/// Prints one row per flight summarising what the file holds. `REDLINE` is
+/// the reading that came closest to its limit in `alarms`. With `si_units`
+/// `MAX CHT` and `FUEL` are converted by `Channel::to_si`.
+fn print_list(
+    header: &EdmHeader,
+    data: &HeaderData,
+    alarms: &Alarms,
+    si_units: bool,
+) -> Result<(), ParseError> {
+    let convert = |channel: Channel, value: f64| match si_units {
+        true => channel.to_si(value).unwrap_or(value),
+        false => value,
+    };
+    let registration = data.registration.as_deref().unwrap_or_default();
+    let flights = header.flights()?;
+    println!(
+        "{:>6}  {:<19}  {:>8}  {:>7}  {:>6}  {:<11}  REGISTRATION",
+        "FLIGHT", "START", "DURATION", "MAX CHT", "FUEL", "REDLINE"
+    );
+    for flight in flights {
+        let stats = flight.stats();
+        println!(
+            "{:>6}  {:<19}  {:>8}  {:>7}  {:>6}  {:<11}  {}",
+            stats.number,
+            stats.start_time.map(format_timestamp).unwrap_or_default(),
+            format!(
+                "{}:{:02}:{:02}",
+                stats.duration_secs / 3600,
+                stats.duration_secs / 60 % 60,
+                stats.duration_secs % 60
             ),
-            max_egt: Some(
-                alarms_itr
-                    .next()?
-                    .parse::<i32>()
-                    .expect("Could not parse alarm"),
-            ),
-            max_oil_temp: Some(
-                alarms_itr
-                    .next()?
-                    .parse::<i32>()
-                    .expect("Could not parse alarm"),
-            ),
-            min_oil_temp: Some(
-                alarms_itr
-                    .next()?
-                    .parse::<i32>()
-                    .expect("Could not parse alarm"),
-            ),
-            ..Default::default()
-        })
-    }
-
-    fn data(&self) -> HeaderData {
-        let headers = self.parse();
-        let mut header_data = HeaderData {
-            ..Default::default()
-        };
-        for line in headers.lines() {
-            match line.chars().nth(1).expect("Empty Header!") {
-                'U' => {
-                    header_data.registration = EdmHeader::process_header_rego(line);
-                }
-                'A' => {
-                    header_data.alarms = EdmHeader::process_header_alarms(line);
-                }
-                _ => (),
-            }
-        }
-        header_data
+            stats
+                .max_cht
+                .map(|c| format!("{:.0}", convert(Channel::Cht(1), f64::from(c))))
+                .unwrap_or_default(),
+            stats
+                .fuel_used
+                .map(|f| format!("{:.1}", convert(Channel::FuelUsed, f)))
+                .unwrap_or_default(),
+            flight
+                .peak_of_redline(alarms)
+                .map(|(channel, percent)| format!("{percent:.0}% {}", channel.label()))
+                .unwrap_or_default(),
+            registration
+        );
     }
+    Ok(())
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let header: EdmHeader = EdmHeader::new("./FILE.JPI")?;
-
-    dbg!(header.data());
-    Ok(())
+/// Prints what the header says about the instrument and how much of it the
+/// decoder understands.
+fn print_version_info(data: &HeaderData) {
+    let Some(features) = &data.features else {
+        println!("No $C config line, flights cannot be decoded");
+        return;
+    };
+    let show = |value: Option<i32>| value.map_or("unknown".to_string(), |v| v.to_string());
+    println!(
+        "Model:          {}",
+        features.model_name().as_deref().unwrap_or("unknown")
+    );
+    println!("Firmware:       {}", show(features.firmware_version));
+    println!(
+        "Flight header:  {}",
+        if features.long_flight_header() {
+            "14 words (EDM-900 and later, supported)"
+        } else {
+            "7 words (older models, assumed and untested)"
+        }
+    );
+    println!("Recording mode: per flight, from each flight's flags and interval");
+    let channels = features
+        .sensors
+        .as_ref()
+        .map(|s| s.field_labels())
+        .unwrap_or_default();
+    println!("Decoded:        {}", channels.join(" "));
+    println!("Not decoded:    HP, fuel settings ($F) and EDM-900 fields from 48 up");
 }
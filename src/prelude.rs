@@ -0,0 +1,25 @@
+//! The types most programs reading JPI files need.
+//!
+//! ```
+//! use jpi::prelude::*;
+//!
+//! let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/4cyl.jpi");
+//! let header = EdmHeader::new(path)?;
+//! let flights: Vec<Flight> = header.flights_with(&DecodeConfig::default())?;
+//! let record: &Record = &flights[0].records[0];
+//! assert_eq!(record.get(Channel::Egt(1)), Some(1154.0));
+//!
+//! let mut csv = Vec::new();
+//! flights[0].write_csv(&mut csv, CsvDialect::Native)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+pub use crate::{
+    channel::Channel,
+    config::DecodeConfig,
+    diagnostic::Diagnostic,
+    error::ParseError,
+    export::{CsvDialect, ExportOptions},
+    flight::{Flight, Record},
+    header::{EdmHeader, HeaderData},
+};
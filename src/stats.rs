@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+
+use crate::{channel::Channel, flight::Flight};
+
+/// A one pass summary of a decoded flight.
+#[derive(Debug, Default)]
+pub struct FlightStats {
+    pub number: i32,
+    pub records: usize,
+    /// Start of the flight in seconds since the Unix epoch.
+    pub start_time: Option<i64>,
+    /// Time from the first record to the last, as `Flight::duration`.
+    pub duration_secs: i64,
+    /// Hottest CHT, ignoring readings from a faulted probe.
+    pub max_cht: Option<i32>,
+    /// Fuel burned, integrated from fuel flow since the totalizer can be
+    /// reset mid flight.
+    pub fuel_used: Option<f64>,
+    /// Channels that read as a probe fault at least once, in decode order.
+    pub faulted_channels: Vec<Channel>,
+}
+
+/// Lowest, highest and mean reading of one channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelStats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Number of readings the figures are taken over.
+    pub count: usize,
+}
+
+impl ChannelStats {
+    fn new(value: f64) -> ChannelStats {
+        ChannelStats {
+            min: value,
+            max: value,
+            mean: value,
+            count: 1,
+        }
+    }
+
+    /// Combines two sets of readings of the same channel.
+    pub fn merge(&self, other: &ChannelStats) -> ChannelStats {
+        let count = self.count + other.count;
+        ChannelStats {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+            mean: (self.mean * self.count as f64 + other.mean * other.count as f64) / count as f64,
+            count,
+        }
+    }
+}
+
+/// Per channel figures over every flight in a file.
+#[derive(Debug, Default)]
+pub struct FileStats {
+    pub flights: usize,
+    pub records: usize,
+    pub channels: BTreeMap<Channel, ChannelStats>,
+}
+
+impl FileStats {
+    pub fn from_flights(flights: &[Flight]) -> FileStats {
+        let mut stats = FileStats::default();
+        for flight in flights {
+            stats.flights += 1;
+            stats.records += flight.records.len();
+            for (channel, channel_stats) in flight.channel_stats() {
+                stats
+                    .channels
+                    .entry(channel)
+                    .and_modify(|s| *s = s.merge(&channel_stats))
+                    .or_insert(channel_stats);
+            }
+        }
+        stats
+    }
+}
+
+impl Flight {
+    pub fn stats(&self) -> FlightStats {
+        let interval = self.header.as_ref().map_or(0, |h| h.interval_secs) as i64;
+        let mut stats = FlightStats {
+            number: self.number,
+            records: self.records.len(),
+            start_time: self.header.as_ref().and_then(|h| h.start_time),
+            duration_secs: self.duration().map_or(0, |d| d.as_secs() as i64),
+            ..Default::default()
+        };
+        for record in &self.records {
+            // A failed probe would otherwise show as the hottest cylinder
+            let cht = (1..=record.cht.len() as u8)
+                .filter(|n| !record.is_fault(Channel::Cht(*n)))
+                .map(|n| record.cht[n as usize - 1])
+                .max();
+            if let Some(cht) = cht {
+                stats.max_cht = Some(stats.max_cht.map_or(cht, |max| max.max(cht)));
+            }
+            for channel in record.faults() {
+                if let Err(i) = stats.faulted_channels.binary_search(&channel) {
+                    stats.faulted_channels.insert(i, channel);
+                }
+            }
+            if let Some(fuel_flow) = record.fuel_flow {
+                *stats.fuel_used.get_or_insert(0.0) += fuel_flow * interval as f64 / 3600.0;
+            }
+        }
+        stats
+    }
+
+    /// Figures for every channel recorded in the flight, skipping readings
+    /// that look like a probe fault.
+    pub fn channel_stats(&self) -> BTreeMap<Channel, ChannelStats> {
+        let mut channels = BTreeMap::<Channel, ChannelStats>::new();
+        for record in &self.records {
+            for channel in record.channels() {
+                if record.is_fault(channel) {
+                    continue;
+                }
+                let Some(value) = record.get(channel) else {
+                    continue;
+                };
+                channels
+                    .entry(channel)
+                    .and_modify(|s| *s = s.merge(&ChannelStats::new(value)))
+                    .or_insert(ChannelStats::new(value));
+            }
+        }
+        channels
+    }
+}
@@ -0,0 +1,67 @@
+/// Seconds since the Unix epoch for a UTC calendar date and time.
+///
+/// The instrument has no notion of time zones, so its clock is treated as UTC.
+pub(crate) fn epoch_seconds(
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+) -> i64 {
+    // Days from civil, shifting the year to start in March so leap days fall last
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    days * 86400 + hour as i64 * 3600 + minute as i64 * 60 + second as i64
+}
+
+/// Calendar date and time for seconds since the Unix epoch, the inverse of
+/// `epoch_seconds`: `(year, month, day, hour, minute, second)`.
+pub(crate) fn civil(timestamp: i64) -> (i64, u32, u32, u32, u32, u32) {
+    let days = timestamp.div_euclid(86400) + 719468;
+    let secs = timestamp.rem_euclid(86400) as u32;
+
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day, secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Reads a time written by `format_timestamp` back as seconds since the
+/// Unix epoch.
+pub(crate) fn parse_timestamp(text: &str) -> Option<i64> {
+    let (date, time) = text.trim().split_once(' ')?;
+    let date = date
+        .split('-')
+        .map(|v| v.parse::<u32>().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    let time = time
+        .split(':')
+        .map(|v| v.parse::<u32>().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    let (&[year, month, day], &[hour, minute, second]) = (&date[..], &time[..]) else {
+        return None;
+    };
+    let timestamp = epoch_seconds(i64::from(year), month, day, hour, minute, second);
+    // Out of range fields would land on another day rather than fail
+    (format_timestamp(timestamp) == text.trim()).then_some(timestamp)
+}
+
+/// Formats seconds since the Unix epoch as `YYYY-MM-DD HH:MM:SS`.
+pub fn format_timestamp(timestamp: i64) -> String {
+    let (year, month, day, hour, minute, second) = civil(timestamp);
+    format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02}")
+}
@@ -0,0 +1,102 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::ParseError,
+    header::{EdmHeader, HeaderData},
+};
+
+/// File extensions `walk_dir` picks up, in any case. Most downloads are
+/// `.jpi`, but some firmware writes `.dat`.
+pub const JPI_EXTENSIONS: [&str; 2] = ["jpi", "dat"];
+
+/// Parses the header of every file under `root` with one of the
+/// `JPI_EXTENSIONS`, one file at a time.
+///
+/// Directories are walked depth first with their entries in name order, so
+/// the same tree always comes back in the same order. Symbolic links are
+/// followed, but each directory is walked only once, so a link back up the
+/// tree does not loop. Only headers are parsed, flights are left for
+/// `EdmHeader::flights`. Files and directories that cannot be read are
+/// skipped; a file that reads but does not parse is returned with its
+/// error.
+pub fn walk_dir<P: AsRef<Path>>(
+    root: P,
+) -> impl Iterator<Item = (PathBuf, Result<HeaderData, ParseError>)> {
+    walk_dir_with(root, &JPI_EXTENSIONS)
+}
+
+/// Like `walk_dir`, picking up files with any of `extensions`, which are
+/// given without the dot and matched in any case.
+pub fn walk_dir_with<'a, P: AsRef<Path>>(
+    root: P,
+    extensions: &'a [&str],
+) -> impl Iterator<Item = (PathBuf, Result<HeaderData, ParseError>)> + 'a {
+    walk(root, extensions, |_| true)
+}
+
+/// Like `walk_dir`, keeping only the files for the aircraft `tail`, such as
+/// `N123AB`, matched in any case. Each file's registration is read with
+/// `EdmHeader::registration_only` before the rest of its header is parsed,
+/// so files for other aircraft cost little. A file whose registration
+/// cannot be read is skipped.
+pub fn walk_dir_for_tail<P: AsRef<Path>>(
+    root: P,
+    tail: &str,
+) -> impl Iterator<Item = (PathBuf, Result<HeaderData, ParseError>)> + '_ {
+    let tail = tail.trim();
+    walk(root, &JPI_EXTENSIONS, move |header| {
+        header
+            .registration_only()
+            .ok()
+            .flatten()
+            .is_some_and(|registration| registration.trim().eq_ignore_ascii_case(tail))
+    })
+}
+
+fn walk<'a, P: AsRef<Path>>(
+    root: P,
+    extensions: &'a [&str],
+    keep: impl Fn(&EdmHeader) -> bool + 'a,
+) -> impl Iterator<Item = (PathBuf, Result<HeaderData, ParseError>)> + 'a {
+    let mut pending = vec![root.as_ref().to_path_buf()];
+    // Directories already walked, by their canonical path
+    let mut visited = HashSet::new();
+    std::iter::from_fn(move || {
+        while let Some(path) = pending.pop() {
+            if path.is_dir() {
+                let Ok(canonical) = fs::canonicalize(&path) else {
+                    continue;
+                };
+                if !visited.insert(canonical) {
+                    continue;
+                }
+                let Ok(entries) = fs::read_dir(&path) else {
+                    continue;
+                };
+                let mut entries = entries
+                    .filter_map(|entry| Some(entry.ok()?.path()))
+                    .collect::<Vec<PathBuf>>();
+                // Popped from the end, so reverse to visit in name order
+                entries.sort_by(|a, b| b.cmp(a));
+                pending.extend(entries);
+            } else if has_extension(&path, extensions) {
+                if let Ok(bytes) = fs::read(&path) {
+                    let header = EdmHeader::from_bytes(bytes);
+                    if keep(&header) {
+                        return Some((path, header.try_data()));
+                    }
+                }
+            }
+        }
+        None
+    })
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
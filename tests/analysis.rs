@@ -0,0 +1,364 @@
+mod common;
+
+use jpi::{
+    cruise_averages, dead_cylinders, disconnected_probes, downsample, egt_rises, egt_spread,
+    estimate_power, gami_spread, AlarmKind, AnalysisError, Channel, ChannelStats, EdmHeader,
+    FileStats, Flight, FlightHeader, Record,
+};
+
+#[test]
+fn egt_spread_ignores_disconnected_probes() {
+    // A 4 cylinder engine on a unit configured for 6 EGTs
+    let record = Record {
+        egt: vec![1320, 1290, 1345, 1302, 0, 240],
+        ..Default::default()
+    };
+    let disconnected = disconnected_probes(std::slice::from_ref(&record));
+    assert_eq!(disconnected, [Channel::Egt(5), Channel::Egt(6)]);
+    assert_eq!(egt_spread(&record, &disconnected), Some(55));
+    assert_eq!(egt_spread(&record, &[]), Some(1345));
+    assert_eq!(egt_spread(&Record::default(), &[]), None);
+
+    // A live probe reading 240 for a moment is part of the spread
+    let records = [1300, 240].map(|egt| Record {
+        egt: vec![1320, 1290, 1345, egt, 0],
+        ..Default::default()
+    });
+    let disconnected = disconnected_probes(&records);
+    assert_eq!(disconnected, [Channel::Egt(5)]);
+    assert_eq!(egt_spread(&records[1], &disconnected), Some(1105));
+}
+
+#[test]
+fn probe_faults() {
+    let mut flight = Flight {
+        header: Some(FlightHeader {
+            interval_secs: 6,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    for cht in [[350, 362, 355, 348], [352, 3000, 356, 349]] {
+        flight.records.push(Record {
+            egt: vec![1310, 1295, 1330, 1302],
+            cht: cht.to_vec(),
+            oil_temp: Some(190),
+            ..Default::default()
+        });
+    }
+    assert_eq!(flight.records[0].faults(), []);
+    assert_eq!(flight.records[1].faults(), [Channel::Cht(2)]);
+    assert!(flight.records[1].is_fault(Channel::Cht(2)));
+    assert!(!flight.records[1].is_fault(Channel::OilTemp));
+
+    let stats = flight.stats();
+    assert_eq!(stats.max_cht, Some(362));
+    assert_eq!(stats.faulted_channels, [Channel::Cht(2)]);
+}
+
+#[test]
+fn file_stats() {
+    let path = format!("{}/tests/fixtures/4cyl.jpi", env!("CARGO_MANIFEST_DIR"));
+    let flights = EdmHeader::new(&path).unwrap().flights().unwrap();
+    let stats = FileStats::from_flights(&flights);
+    assert_eq!(stats.flights, 2);
+    assert_eq!(stats.records, 21);
+
+    for channel in [Channel::Cht(3), Channel::Egt(1), Channel::OilTemp] {
+        let per_flight = flights
+            .iter()
+            .map(|f| f.channel_stats()[&channel])
+            .collect::<Vec<ChannelStats>>();
+        let file = stats.channels[&channel];
+        assert_eq!(file.max, per_flight[0].max.max(per_flight[1].max));
+        assert_eq!(file.min, per_flight[0].min.min(per_flight[1].min));
+        assert_eq!(file.count, 21);
+    }
+    assert_eq!(stats.channels[&Channel::Cht(3)].max, 251.0);
+}
+
+#[test]
+fn downsample_buckets() {
+    // 2 second data, downsampled to 6 second buckets
+    let records = (0..12)
+        .map(|i| Record {
+            timestamp: Some(1_000 + 2 * i as i64),
+            egt: vec![1300 + i, 1400 - i],
+            cht: vec![350 + i % 3, 340],
+            fuel_flow: Some(10.0 + f64::from(i % 3)),
+            fuel_used: Some(f64::from(i) / 10.0),
+            rpm: Some(2400 + 10 * (i % 3)),
+            ..Default::default()
+        })
+        .collect::<Vec<Record>>();
+    let buckets = downsample(&records, 6);
+    assert_eq!(buckets.len(), records.len() / 3);
+
+    let second = &buckets[1];
+    assert_eq!(second.timestamp, Some(1_006));
+    assert_eq!(second.egt, [1305, 1397]);
+    assert_eq!(second.cht, [352, 340]);
+    assert_eq!(second.fuel_flow, Some(11.0));
+    assert_eq!(second.fuel_used, Some(0.5));
+    assert_eq!(second.rpm, Some(2410));
+}
+
+#[test]
+fn dead_cylinder() {
+    let healthy = common::flight(1, 20);
+    assert_eq!(dead_cylinders(&healthy.records), []);
+
+    let mut records = Vec::new();
+    // Cylinder 2 is slow to light off while taxiing, which does not count
+    for _ in 0..5 {
+        records.push(Record {
+            egt: vec![900, 500, 880, 910],
+            cht: vec![250, 240, 255, 245],
+            rpm: Some(1000),
+            ..Default::default()
+        });
+    }
+    for i in 0..10 {
+        records.push(Record {
+            egt: vec![1350 + i, 1340, 640 + i, 1360],
+            cht: vec![360, 355, 340 + i, 350],
+            rpm: Some(2400),
+            ..Default::default()
+        });
+    }
+    let dead = dead_cylinders(&records);
+    assert_eq!(dead.iter().map(|d| d.cylinder).collect::<Vec<u8>>(), [3]);
+    assert_eq!(dead[0].records, 10);
+    assert!((dead[0].egt_deficit - 707.0).abs() < 1.0);
+    assert!(dead[0].cht_deviation.abs() < 20.0);
+
+    // A cold head as well is not a misfire
+    for record in &mut records {
+        record.cht[2] = 200;
+    }
+    assert_eq!(dead_cylinders(&records), []);
+
+    // A cool winter cruise with the misfiring head passing through 240°,
+    // next to two probes that are not connected at all
+    for (i, record) in records.iter_mut().enumerate() {
+        record.cht = vec![255, 250, 238 + i as i32 % 5, 245, 0, 240];
+        record.egt.extend([0, 240]);
+    }
+    assert_eq!(
+        disconnected_probes(&records),
+        [
+            Channel::Egt(5),
+            Channel::Egt(6),
+            Channel::Cht(5),
+            Channel::Cht(6)
+        ]
+    );
+    let dead = dead_cylinders(&records);
+    assert_eq!(dead.iter().map(|d| d.cylinder).collect::<Vec<u8>>(), [3]);
+    assert_eq!(dead[0].records, 10);
+}
+
+#[test]
+fn gami_spread_over_lean_sweep() {
+    // Cruise at 14.0, lean to 10.2 in steps of 0.2, then back to 14.0
+    let tenths = (0..5)
+        .map(|_| 140)
+        .chain((1..=19).map(|i| 140 - 2 * i))
+        .chain([140, 140]);
+    // Each cylinder peaks at its own flow, cylinder 3 richest
+    let peaks = [120, 116, 124, 118];
+    let records = tenths
+        .map(|ff| Record {
+            egt: peaks
+                .iter()
+                .map(|peak| 1450 - (ff - peak) * (ff - peak))
+                .collect(),
+            fuel_flow: Some(f64::from(ff) / 10.0),
+            ..Default::default()
+        })
+        .collect::<Vec<Record>>();
+
+    let gami = gami_spread(&records).unwrap();
+    assert_eq!(gami.sweep, (0, 23));
+    assert_eq!(gami.peak_fuel_flow, [12.0, 11.6, 12.4, 11.8]);
+    assert!((gami.spread - 0.8).abs() < 1e-9);
+
+    // Stopping before cylinder 2 peaks is not a complete sweep
+    assert_eq!(gami_spread(&records[..15]), None);
+    assert_eq!(gami_spread(&common::flight(1, 20).records), None);
+}
+
+#[test]
+fn egt_rise_rate() {
+    let mut records = common::flight(1, 20).records;
+    assert_eq!(egt_rises(&records, 60.0), []);
+
+    // Cylinder 2 climbs 50° then 60° in six seconds and holds
+    records[9].egt[1] = 1360;
+    for record in &mut records[10..] {
+        record.egt[1] = 1420;
+    }
+    let rises = egt_rises(&records, 60.0);
+    assert_eq!(rises.len(), 1);
+    assert_eq!(rises[0].cylinder, 2);
+    assert_eq!(rises[0].span, (8, 10));
+    assert!((rises[0].peak_rate - 600.0).abs() < 1e-9);
+
+    // Below the threshold, and without timestamps to measure against
+    assert_eq!(egt_rises(&records, 600.0), []);
+    for record in &mut records {
+        record.timestamp = None;
+    }
+    assert_eq!(egt_rises(&records, 60.0), []);
+
+    // Lighting off through 240° is a rise like any other
+    let mut records = common::flight(1, 4).records;
+    for (record, egt) in records.iter_mut().zip([180, 240, 330, 420]) {
+        record.egt[0] = egt;
+    }
+    let rises = egt_rises(&records, 60.0);
+    assert_eq!(rises.len(), 1);
+    assert_eq!(rises[0].span, (0, 3));
+    assert!((rises[0].peak_rate - 900.0).abs() < 1e-9);
+}
+
+#[test]
+fn cruise_segment_averages() {
+    let phase = |count: usize, rpm: fn(i32) -> i32, map: fn(i32) -> f64, egt, cht, ff| {
+        (0..count as i32)
+            .map(move |i| Record {
+                egt: vec![egt, egt + 10, egt - 10, egt],
+                cht: vec![cht, cht, cht + 20, cht - 20],
+                fuel_flow: Some(ff),
+                map: Some(map(i)),
+                rpm: Some(rpm(i)),
+                ..Default::default()
+            })
+            .collect::<Vec<Record>>()
+    };
+    let mut records = phase(8, |_| 2700, |i| 28.5 - 0.2 * f64::from(i), 1250, 390, 17.0);
+    records.extend(phase(30, |i| 2400 + i % 11, |_| 23.5, 1400, 350, 9.8));
+    records.extend(phase(
+        10,
+        |i| 2300 - 100 * i,
+        |i| 20.0 - f64::from(i),
+        1100,
+        300,
+        5.0,
+    ));
+
+    let cruise = cruise_averages(&records).unwrap();
+    assert_eq!(cruise.span, (8, 37));
+    assert_eq!(
+        cruise.egt,
+        [Some(1400.0), Some(1410.0), Some(1390.0), Some(1400.0)]
+    );
+    assert_eq!(
+        cruise.cht,
+        [Some(350.0), Some(350.0), Some(370.0), Some(330.0)]
+    );
+    assert!((cruise.fuel_flow.unwrap() - 9.8).abs() < 1e-9);
+
+    // A cool head sitting at 240° in cruise still counts toward its mean
+    let mut cool = records.clone();
+    for (i, record) in cool[8..38].iter_mut().enumerate() {
+        record.cht[3] = if i % 2 == 0 { 240 } else { 250 };
+    }
+    assert_eq!(cruise_averages(&cool).unwrap().cht[3], Some(245.0));
+
+    // Too short a flight has no cruise, nor does one without RPM
+    assert_eq!(cruise_averages(&records[..17]), None);
+    for record in &mut records {
+        record.rpm = None;
+    }
+    assert_eq!(cruise_averages(&records), None);
+}
+
+#[test]
+fn redline_overrides() {
+    let record = &common::flight(1, 1).records[0];
+    let mut alarms = common::file(&[common::flight(1, 1)]).data().alarms.unwrap();
+    // CHT1 reads 350 against the file's 460 limit
+    let percent = alarms.percent_of_redline(record, Channel::Cht(1)).unwrap();
+    assert!((percent - 350.0 / 460.0 * 100.0).abs() < 1e-9);
+
+    alarms.set_redline("cht", 420.0).unwrap();
+    assert_eq!(alarms.max_cht, Some(420));
+    assert_eq!(alarms.redline(Channel::Cht(4)), Some(420.0));
+    let percent = alarms.percent_of_redline(record, Channel::Cht(1)).unwrap();
+    assert!((percent - 350.0 / 420.0 * 100.0).abs() < 1e-9);
+    assert_eq!(alarms.percent_of_redline(record, Channel::Rpm), None);
+    // 14.2 of 15.5 volts runs closer than any CHT to 420, but not to 380
+    let flight = common::flight(1, 3);
+    let (channel, _) = flight.peak_of_redline(&alarms).unwrap();
+    assert_eq!(channel, Channel::Volts);
+    alarms.set_redline("CHT", 380.0).unwrap();
+    let (channel, percent) = flight.peak_of_redline(&alarms).unwrap();
+    assert!(matches!(channel, Channel::Cht(_)));
+    assert!(percent > 90.0 && percent < 100.0);
+
+    assert_eq!(
+        alarms.set_redline("TIT", 1650.0),
+        Err(AnalysisError::UnknownRedline {
+            name: "TIT".to_string(),
+            available: ["CHT", "EGT", "OIL", "VOLTS"].map(str::to_string).to_vec(),
+        })
+    );
+    assert_eq!(
+        alarms.set_redline("EGT", -1.0),
+        Err(AnalysisError::InvalidRedline {
+            name: "EGT".to_string(),
+            value: -1.0,
+        })
+    );
+    assert!(alarms.set_redline("EGT", f64::NAN).is_err());
+    assert_eq!(alarms.max_egt, Some(1650));
+}
+
+#[test]
+fn alarm_violations() {
+    let mut flight = common::flight(1, 4);
+    let alarms = common::file(&[common::flight(1, 1)]).data().alarms.unwrap();
+    assert!(flight.alarm_violations(&alarms).iter().all(Vec::is_empty));
+
+    flight.records[1].cht[2] = 470;
+    flight.records[2].volts = Some(12.5);
+    // 470 to 355 in six seconds
+    let violations = flight.alarm_violations(&alarms);
+    assert_eq!(violations[0], []);
+    assert_eq!(violations[1], [AlarmKind::MaxCht]);
+    assert_eq!(
+        violations[2],
+        [AlarmKind::MinVolts, AlarmKind::MaxChtCoolRate]
+    );
+    assert_eq!(violations[3], []);
+
+    // A failed probe is a fault, not an over temperature
+    flight.records[3].cht[0] = 3000;
+    assert_eq!(alarms.violations(&flight.records[3], &[]), []);
+}
+
+#[test]
+fn cruise_power_estimate() {
+    let cruise = Record {
+        map: Some(23.5),
+        rpm: Some(2400),
+        oat: Some(59),
+        ..Default::default()
+    };
+    let estimate = estimate_power(&cruise, 180.0).unwrap();
+    assert!((60.0..70.0).contains(&estimate.percent), "{estimate:?}");
+    assert!((estimate.horsepower - estimate.percent * 1.8).abs() < 1e-9);
+
+    // Colder, denser air makes more power at the same settings
+    let cold = Record {
+        oat: Some(0),
+        ..cruise.clone()
+    };
+    assert!(estimate_power(&cold, 180.0).unwrap().percent > estimate.percent);
+    let no_oat = Record {
+        oat: None,
+        ..cruise
+    };
+    assert_eq!(estimate_power(&no_oat, 180.0), None);
+}
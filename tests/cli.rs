@@ -0,0 +1,211 @@
+use std::process::Command;
+
+fn run(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_jpi"))
+        .args(args)
+        .output()
+        .expect("Could not run jpi");
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"))
+}
+
+#[test]
+fn version_info() {
+    let output = run(&["--version-info", &fixture("6cyl.jpi")]);
+    assert!(output.contains("Model:          EDM-900"));
+    assert!(output.contains("Firmware:       140"));
+    assert!(output.contains("EGT6"));
+}
+
+#[test]
+fn list_flights() {
+    let output = run(&["--list", &fixture("4cyl.jpi")]);
+    let rows = output.lines().collect::<Vec<&str>>();
+    assert!(rows[0].starts_with("FLIGHT"));
+    // One row per $D flight, each ending in the $U registration
+    assert_eq!(rows.len(), 3);
+    assert!(rows[1]
+        .trim_start()
+        .starts_with("3  2022-07-19 22:13:12   0:01:30"));
+    assert!(rows[2].trim_start().starts_with("4  "));
+    assert!(rows[1..].iter().all(|row| row.ends_with("N75278")));
+
+    assert_eq!(run(&[&fixture("4cyl.jpi")]), output);
+}
+
+#[test]
+fn list_si() {
+    // Flight 3 peaks at 272 °F and burns about a tenth of a gallon
+    let output = run(&["--list", "--si", &fixture("4cyl.jpi")]);
+    let row = output.lines().nth(1).unwrap();
+    let columns = row.split_whitespace().collect::<Vec<&str>>();
+    assert_eq!(columns[4..6], ["133", "0.4"]);
+}
+
+#[test]
+fn redline_override() {
+    // Flight 3 peaks at 272 on CHT2, under the $A limit
+    let default = run(&[&fixture("4cyl.jpi")]);
+    assert!(default.lines().nth(1).unwrap().contains("97% VOLTS"));
+    let output = run(&["--redline", "CHT=250", &fixture("4cyl.jpi")]);
+    let rows = output.lines().collect::<Vec<&str>>();
+    assert!(rows[1].contains("109% CHT2"));
+    assert!(rows[2].contains("97% VOLTS"));
+
+    for (redline, error) in [
+        ("TIT=1650", "unknown redline \"TIT\""),
+        ("CHT=-1", "is not a positive number"),
+        ("CHT", "is not NAME=VALUE"),
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_jpi"))
+            .args(["--redline", redline, &fixture("4cyl.jpi")])
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains(error));
+    }
+}
+
+#[test]
+fn self_test() {
+    let output = run(&["--self-test", "3", &fixture("4cyl.jpi")]);
+    assert!(output.contains("flight: 3,"));
+    assert!(output.contains("values_match: true,"));
+}
+
+#[test]
+fn csv_columns() {
+    let output = run(&["--csv", "7", "--columns", "cht", &fixture("6cyl.jpi")]);
+    assert_eq!(
+        output.lines().next(),
+        Some("TIME,CHT1,CHT2,CHT3,CHT4,CHT5,CHT6")
+    );
+    assert_eq!(output.lines().count(), 13);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_jpi"))
+        .args(["--csv", "7", "--columns", "CHT,EGT9", &fixture("6cyl.jpi")])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown column \"EGT9\""));
+}
+
+#[test]
+fn csv_all_flights() {
+    let output = run(&["--csv", "all", "--columns", "EGT1", &fixture("4cyl.jpi")]);
+    let rows = output.lines().collect::<Vec<&str>>();
+    assert_eq!(rows[0], "FLIGHT,TIME,EGT1");
+    let flights = rows[1..]
+        .iter()
+        .map(|row| row.split(',').next().unwrap())
+        .collect::<std::collections::BTreeSet<&str>>();
+    assert_eq!(flights.len(), 2);
+}
+
+#[test]
+fn si_csv() {
+    let output = run(&[
+        "--csv",
+        "7",
+        "--si",
+        "--columns",
+        "CHT1",
+        &fixture("6cyl.jpi"),
+    ]);
+    let fahrenheit = run(&["--csv", "7", "--columns", "CHT1", &fixture("6cyl.jpi")]);
+    let cht = |output: &str| {
+        output
+            .lines()
+            .nth(1)
+            .unwrap()
+            .split(',')
+            .nth(1)
+            .unwrap()
+            .parse::<f64>()
+            .unwrap()
+    };
+    assert!(((cht(&fahrenheit) - 32.0) * 5.0 / 9.0 - cht(&output)).abs() < 0.05);
+}
+
+#[test]
+fn json_output() {
+    let compact = run(&["--json", "7", &fixture("6cyl.jpi")]);
+    assert_eq!(compact.lines().count(), 1);
+    assert!(compact.starts_with("{\"number\":7,\"start_time\":"));
+    assert!(compact.contains("\"EGT1\":"));
+
+    let pretty = run(&["--json", "7", "--pretty", &fixture("6cyl.jpi")]);
+    assert!(pretty.lines().count() > 12);
+    assert!(pretty.starts_with("{\n  \"number\": 7,\n"));
+
+    // 1250°F
+    let si = run(&["--json", "7", "--si", &fixture("6cyl.jpi")]);
+    assert!(si.contains("\"EGT1\":676.66"));
+    let summary = run(&["--summary", "--si", &fixture("6cyl.jpi")]);
+    assert_ne!(summary, run(&["--summary", &fixture("6cyl.jpi")]));
+}
+
+#[test]
+fn json_schema() {
+    let flight = run(&["--json-schema"]);
+    assert!(flight.contains("\"title\": \"jpi flight\""));
+    let summary = run(&["--summary", "--json-schema"]);
+    assert!(summary.contains("\"title\": \"jpi file summary\""));
+    assert!(summary.contains("\"flight_count\""));
+}
+
+#[test]
+fn scan_one_tail() {
+    let root = std::env::temp_dir().join(format!("jpi-cli-tail-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::copy(fixture("6cyl.jpi"), root.join("6cyl.jpi")).unwrap();
+    std::fs::copy(fixture("4cyl.jpi"), root.join("4cyl.jpi")).unwrap();
+    let all = run(&[root.to_str().unwrap()]);
+    let one = run(&["--tail", "N6CYL", root.to_str().unwrap()]);
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(all.lines().count(), 2);
+    assert_eq!(one.lines().count(), 1);
+    assert!(one.contains("6cyl.jpi  N6CYL"));
+}
+
+#[test]
+fn unreadable_header() {
+    let path = std::env::temp_dir().join(format!("jpi-cli-header-{}.jpi", std::process::id()));
+    // An $A line with none of its eight limits
+    std::fs::write(&path, b"$U,N75278*37\r\n$A,HOT*00\r\n$L,0*4C\r\n\0").unwrap();
+    for mode in ["--version-info", "--list", "--summary"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_jpi"))
+            .args([mode, path.to_str().unwrap()])
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success());
+        assert!(
+            stderr.starts_with("Error: header line 2 ($A)"),
+            "{mode}: {stderr}"
+        );
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn unknown_option() {
+    // A mistyped option is not taken as the file to read
+    let output = Command::new(env!("CARGO_BIN_EXE_jpi"))
+        .args(["--lsit", &fixture("4cyl.jpi")])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.starts_with("Error: unknown option --lsit"));
+    assert!(stderr.contains("Usage: jpi"));
+    assert!(output.stdout.is_empty());
+
+    assert!(run(&["--help"]).starts_with("Usage: jpi"));
+}
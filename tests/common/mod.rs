@@ -0,0 +1,106 @@
+//! Builds JPI files in memory for tests that need data the fixtures lack.
+
+#![allow(dead_code)]
+
+use jpi::{EdmHeader, Flight, FlightHeader, Record, Sensors};
+
+/// Flag word for a 4 cylinder engine with volts, oil temp, OAT, fuel flow,
+/// MAP and RPM.
+pub const FLAGS: u32 = 1 | 0x3c | 0x7800 | 1 << 20 | 1 << 25 | 1 << 26 | 1 << 27 | 1 << 30;
+
+fn line(body: &str) -> String {
+    let checksum = body.bytes().fold(0u8, |sum, b| sum ^ b);
+    format!("${body}*{checksum:02X}\r\n")
+}
+
+/// A flight of `count` cruise records six seconds apart.
+pub fn flight(number: i32, count: usize) -> Flight {
+    flight_at(number, count, 1_700_000_000)
+}
+
+/// Like `flight`, starting at `start` seconds since the Unix epoch, which
+/// must be even to survive the flight header's two second resolution.
+pub fn flight_at(number: i32, count: usize, start: i64) -> Flight {
+    let records = (0..count as i32)
+        .map(|i| Record {
+            timestamp: Some(start + 6 * i as i64),
+            egt: vec![1300 + i % 7, 1310, 1290 + i % 3, 1320],
+            cht: vec![350, 360 + i % 5, 355, 345],
+            oil_temp: Some(185),
+            volts: Some(14.2),
+            oat: Some(50),
+            fuel_used: Some(f64::from(i / 6) / 10.0),
+            fuel_flow: Some(9.8),
+            map: Some(23.5),
+            rpm: Some(2400 + i % 11),
+            ..Default::default()
+        })
+        .collect();
+    Flight {
+        number,
+        header: Some(FlightHeader {
+            number,
+            flags: FLAGS,
+            sensors: Sensors::from_flags(FLAGS),
+            interval_secs: 6,
+            start_time: Some(start),
+        }),
+        records,
+        ..Default::default()
+    }
+}
+
+/// Header lines, without `$` and checksum, for an EDM-900 with the `$C`
+/// flags set to `FLAGS`.
+pub fn header_lines() -> Vec<String> {
+    vec![
+        "U,N12345".to_string(),
+        "A,155,130,500,460,60,1650,230,90".to_string(),
+        format!(
+            "C,900,{},{},1048,8418,120,140,2011,10",
+            FLAGS & 0xffff,
+            FLAGS >> 16
+        ),
+    ]
+}
+
+/// An EDM-900 file holding `flights`.
+pub fn file(flights: &[Flight]) -> EdmHeader {
+    file_with(&header_lines(), flights)
+}
+
+/// A file with the given header lines followed by the `$D` index and
+/// `flights`.
+pub fn file_with(lines: &[String], flights: &[Flight]) -> EdmHeader {
+    let blocks = flights
+        .iter()
+        .map(|flight| {
+            let block = flight.encode(true).expect("Could not encode flight");
+            (flight.number, block)
+        })
+        .collect::<Vec<(i32, Vec<u8>)>>();
+    file_with_blocks(lines, &blocks)
+}
+
+/// A file with the given header lines followed by flight blocks that are
+/// already encoded, each with its flight number.
+pub fn file_with_blocks(lines: &[String], flights: &[(i32, Vec<u8>)]) -> EdmHeader {
+    EdmHeader::from_bytes(file_bytes(lines, flights, &[]))
+}
+
+/// The bytes of `file_with_blocks`, with `preamble` written between the
+/// header and the first block.
+pub fn file_bytes(lines: &[String], flights: &[(i32, Vec<u8>)], preamble: &[u8]) -> Vec<u8> {
+    let mut header = lines.iter().map(|body| line(body)).collect::<String>();
+    let mut blocks = Vec::<u8>::new();
+    for (number, block) in flights {
+        header += &line(&format!("D,{number},{}", block.len().div_ceil(2)));
+        blocks.extend(block);
+    }
+    header += &line("L,0");
+    let mut file_stream = header.into_bytes();
+    file_stream.extend(preamble);
+    file_stream.extend(blocks);
+    file_stream.extend(b"$E,4*5D\r\n");
+    file_stream
+}
@@ -0,0 +1,875 @@
+mod common;
+
+use std::time::Duration;
+
+use jpi::{
+    Channel, CsvDialect, DecodeConfig, Diagnostic, EdmHeader, FlightHeader, ParseError, Record,
+    Sensors,
+};
+
+#[test]
+fn record_limit() {
+    let file = common::file(&[common::flight(1, 10), common::flight(2, 5000)]);
+    assert_eq!(file.flights().unwrap()[1].records.len(), 5000);
+
+    let options = DecodeConfig {
+        max_records_per_flight: Some(1000),
+        ..Default::default()
+    };
+    assert_eq!(
+        file.flights_with(&options).unwrap_err(),
+        ParseError::RecordLimit {
+            flight: 2,
+            limit: 1000
+        }
+    );
+
+    let options = DecodeConfig {
+        max_records_per_flight: Some(5000),
+        ..Default::default()
+    };
+    assert!(file.flights_with(&options).is_ok());
+}
+
+#[test]
+fn flights_recorded_with_different_settings() {
+    // A second flight recorded every minute with only EGT, CHT and volts
+    const SPARSE: u32 = 1 | 0x3c | 0x7800;
+    let mut sparse = common::flight(2, 4);
+    let header = sparse.header.as_mut().unwrap();
+    header.flags = SPARSE;
+    header.sensors = Sensors::from_flags(SPARSE);
+    header.interval_secs = 60;
+    for record in &mut sparse.records {
+        *record = Record {
+            timestamp: record.timestamp,
+            egt: record.egt.clone(),
+            cht: record.cht.clone(),
+            volts: record.volts,
+            ..Default::default()
+        };
+    }
+    let file = common::file(&[common::flight(1, 4), sparse]);
+    let flights = file.flights().unwrap();
+
+    let full = &flights[0].records[1];
+    assert_eq!(full.rpm, Some(2401));
+    assert_eq!(full.fuel_flow, Some(9.8));
+    assert_eq!(flights[0].diagnostics, []);
+
+    let sparse = &flights[1];
+    assert_eq!(sparse.header.as_ref().unwrap().interval_secs, 60);
+    assert_eq!(sparse.records[1].egt, [1301, 1310, 1291, 1320]);
+    assert_eq!(sparse.records[1].volts, Some(14.2));
+    assert_eq!(sparse.records[1].rpm, None);
+    assert_eq!(sparse.records[1].fuel_flow, None);
+    assert_eq!(
+        sparse.records[3].timestamp.unwrap() - sparse.records[0].timestamp.unwrap(),
+        180
+    );
+    assert!(matches!(
+        sparse.diagnostics[..],
+        [Diagnostic::SensorMismatch { .. }]
+    ));
+}
+
+#[test]
+fn flight_without_fuel_flow() {
+    // The transducer unplugged for the second flight only, so $C still
+    // lists it
+    const NO_FUEL_FLOW: u32 = common::FLAGS & !(1 << 27);
+    let mut flight = common::flight(2, 4);
+    let header = flight.header.as_mut().unwrap();
+    header.flags = NO_FUEL_FLOW;
+    header.sensors = Sensors::from_flags(NO_FUEL_FLOW);
+    for record in &mut flight.records {
+        record.fuel_flow = None;
+        record.fuel_used = None;
+    }
+    let file = common::file(&[common::flight(1, 4), flight]);
+    let flights = file.flights().unwrap();
+    assert!(file.data().features.unwrap().sensors.unwrap().fuel_flow);
+
+    let sensors = &flights[1].header.as_ref().unwrap().sensors;
+    assert!(!sensors.fuel_flow);
+    assert!(!sensors.present().contains(&Channel::FuelFlow));
+    for record in &flights[1].records {
+        assert_eq!((record.fuel_flow, record.fuel_used), (None, None));
+        // Every other channel still decodes in place
+        assert_eq!(record.map, Some(23.5));
+        assert_eq!(record.volts, Some(14.2));
+    }
+    assert_eq!(flights[1].records[1].rpm, Some(2401));
+    assert_eq!(flights[1].stats().fuel_used, None);
+    assert_eq!(flights[0].records[1].fuel_flow, Some(9.8));
+    assert!(matches!(
+        flights[1].diagnostics[..],
+        [Diagnostic::SensorMismatch { .. }]
+    ));
+}
+
+#[test]
+fn probe_disabled_for_one_flight() {
+    // EGT 4 turned off for the second flight
+    const THREE_EGTS: u32 = common::FLAGS & !(1 << 14);
+    let mut flight = common::flight(2, 4);
+    let header = flight.header.as_mut().unwrap();
+    header.flags = THREE_EGTS;
+    header.sensors = Sensors::from_flags(THREE_EGTS);
+    for record in &mut flight.records {
+        record.egt.truncate(3);
+    }
+    let file = common::file(&[common::flight(1, 4), flight]);
+    let config = file.data().features.unwrap().sensors.unwrap();
+    let flights = file.flights().unwrap();
+
+    assert_eq!(config.egt_count, Some(4));
+    assert_eq!(flights[0].active_egt_count(), Some(4));
+    assert_eq!(flights[1].active_egt_count(), Some(3));
+    assert_eq!(flights[1].active_cht_count(), Some(4));
+    assert_eq!(flights[1].records[1].egt, [1301, 1310, 1291]);
+    assert_eq!(common::flight(3, 1).active_egt_count(), Some(4));
+    assert_eq!(file.data().flights[0].active_egt_count(), None);
+
+    // The mismatch is reported, and fails the decode if asked to
+    assert_eq!(flights[0].diagnostics, []);
+    let Diagnostic::SensorMismatch {
+        config, recorded, ..
+    } = &flights[1].diagnostics[0]
+    else {
+        panic!("no mismatch in {:?}", flights[1].diagnostics);
+    };
+    assert_eq!((config.egt_count, recorded.egt_count), (Some(4), Some(3)));
+    let strict = DecodeConfig {
+        strict_probe_counts: true,
+        ..Default::default()
+    };
+    let error = file.flights_with(&strict).unwrap_err();
+    assert_eq!(
+        error,
+        ParseError::ProbeCountMismatch {
+            flight: 2,
+            egt: (4, 3),
+            cht: (4, 4),
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "flight 2: records 3 EGT and 4 CHT probes, $C has 4 and 4"
+    );
+    let file = common::file(&[common::flight(1, 4)]);
+    assert_eq!(file.flights_with(&strict).unwrap()[0].records.len(), 4);
+}
+
+#[test]
+fn edm_930_file() {
+    // The EDM-930 primary instrument has no layout of its own
+    let lines = common::header_lines()
+        .into_iter()
+        .map(|line| line.replace("C,900,", "C,930,"))
+        .collect::<Vec<String>>();
+    let file = common::file_with(&lines, &[common::flight(1, 6)]);
+    let features = file.data().features.unwrap();
+    assert_eq!(features.model_name().as_deref(), Some("EDM-930"));
+    assert!(features.long_flight_header());
+
+    let flights = file.flights().unwrap();
+    let expected = common::file(&[common::flight(1, 6)]).flights().unwrap();
+    assert_eq!(flights[0].diagnostics, []);
+    assert_eq!(flights[0].records, expected[0].records);
+}
+
+#[test]
+fn unset_clock() {
+    let mut flight = common::flight(1, 3);
+    flight.header.as_mut().unwrap().start_time = None;
+    let mut lines = common::header_lines();
+    lines.push("T,0,0,0,0,0,0".to_string());
+    let file = common::file_with(&lines, &[flight]);
+    assert_eq!(file.data().download_time, None);
+
+    let flights = file.flights().unwrap();
+    assert_eq!(flights[0].header.as_ref().unwrap().start_time, None);
+    assert!(flights[0].records.iter().all(|r| r.timestamp.is_none()));
+    assert_eq!(flights[0].records[2].rpm, Some(2402));
+    let mut csv = Vec::new();
+    flights[0].write_csv(&mut csv, CsvDialect::Native).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.lines().skip(1).all(|row| row.starts_with(",1")));
+}
+
+#[test]
+fn merge_split_flights() {
+    let start = 1_700_000_000;
+    // Flight 2 starts ten seconds after flight 1's last record at +54
+    let file = common::file(&[
+        common::flight_at(1, 10, start),
+        common::flight_at(2, 5, start + 64),
+        common::flight_at(3, 4, start + 3600),
+    ]);
+    assert_eq!(file.flights().unwrap().len(), 3);
+
+    let options = DecodeConfig {
+        merge_within_secs: Some(60),
+        ..Default::default()
+    };
+    let flights = file.flights_with(&options).unwrap();
+    assert_eq!(
+        flights.iter().map(|f| f.number).collect::<Vec<i32>>(),
+        [1, 3]
+    );
+    assert_eq!(flights[0].records.len(), 15);
+    assert_eq!(flights[0].records[10].timestamp, Some(start + 64));
+    assert_eq!(flights[1].records.len(), 4);
+
+    let options = DecodeConfig {
+        merge_within_secs: Some(5),
+        ..Default::default()
+    };
+    assert_eq!(file.flights_with(&options).unwrap().len(), 3);
+
+    // A second half that recorded only EGT, CHT and volts is left apart
+    const SPARSE: u32 = 1 | 0x3c | 0x7800;
+    let mut sparse = common::flight_at(2, 5, start + 64);
+    let header = sparse.header.as_mut().unwrap();
+    header.flags = SPARSE;
+    header.sensors = Sensors::from_flags(SPARSE);
+    for record in &mut sparse.records {
+        *record = Record {
+            timestamp: record.timestamp,
+            egt: record.egt.clone(),
+            cht: record.cht.clone(),
+            volts: record.volts,
+            ..Default::default()
+        };
+    }
+    let file = common::file(&[common::flight_at(1, 10, start), sparse]);
+    let options = DecodeConfig {
+        merge_within_secs: Some(60),
+        ..Default::default()
+    };
+    let flights = file.flights_with(&options).unwrap();
+    assert_eq!(
+        flights.iter().map(|f| f.number).collect::<Vec<i32>>(),
+        [1, 2]
+    );
+}
+
+#[test]
+fn pilot_mark() {
+    let mut flight = common::flight(1, 6);
+    flight.records[2].mark = 2;
+    flight.records[4].mark = 3;
+    let flights = common::file(&[flight]).flights().unwrap();
+    let marked = flights[0]
+        .records
+        .iter()
+        .map(Record::is_marked)
+        .collect::<Vec<bool>>();
+    assert_eq!(marked, [false, false, true, false, true, false]);
+    assert_eq!(
+        flights[0].mark_events(),
+        [(Some(1_700_000_012), 2), (Some(1_700_000_024), 4)]
+    );
+
+    let mut csv = Vec::new();
+    flights[0].write_csv(&mut csv, CsvDialect::Native).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let rows = csv.lines().collect::<Vec<&str>>();
+    assert!(rows[0].ends_with(",MARK"));
+    assert!(rows[3].ends_with(",1"));
+    assert!(rows[4].ends_with(",0"));
+}
+
+#[test]
+fn flights_between() {
+    const DAY: i64 = 86_400;
+    let start = 1_700_000_000;
+    let file = common::file(&[
+        common::flight_at(1, 3, start),
+        common::flight_at(2, 3, start + DAY),
+        common::flight_at(3, 3, start + 2 * DAY),
+    ]);
+    let numbers = |from, to| {
+        file.flights_between(from, to)
+            .unwrap()
+            .iter()
+            .map(|f| f.number)
+            .collect::<Vec<i32>>()
+    };
+    assert_eq!(numbers(start + DAY - 60, start + DAY + 60), [2]);
+    assert_eq!(numbers(start + DAY, start + 2 * DAY), [2]);
+    assert_eq!(numbers(start, start + 3 * DAY), [1, 2, 3]);
+    assert_eq!(numbers(0, start), []);
+}
+
+#[test]
+fn record_round_trip() {
+    let record = Record {
+        timestamp: Some(1_700_000_006),
+        egt: vec![1320, 1290, 1345, 1302],
+        cht: vec![355, 362, 348, 351],
+        oil_temp: Some(188),
+        volts: Some(14.1),
+        fuel_flow: Some(9.6),
+        rpm: Some(2410),
+        ..Default::default()
+    };
+    // OAT and MAP are recorded by the flight but not set here, so they
+    // carry over from the first record
+    let mut flight = common::flight(1, 2);
+    flight.records[1] = record.clone();
+    let decoded = common::file(&[flight]).flights().unwrap();
+    assert_eq!(decoded[0].records[1].egt, record.egt);
+    assert_eq!(decoded[0].records[1].volts, record.volts);
+    let mut csv = Vec::new();
+    decoded[0].write_csv(&mut csv, CsvDialect::Native).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(
+        csv.lines().nth(2),
+        Some("2023-11-14 22:13:26,1320,1290,1345,1302,355,362,348,351,188,14.1,50,0.0,9.6,23.5,2410,0")
+    );
+}
+
+#[test]
+fn high_resolution_fuel_flow() {
+    // 9.8 gph stored in hundredths reads as 98.0 when taken as tenths. The
+    // field has no high byte, so flow ramps up from where records start.
+    let mut flight = common::flight(1, 600);
+    for (i, record) in flight.records.iter_mut().enumerate() {
+        record.fuel_flow = Some((24.0 + 2.0 * i as f64).min(98.0));
+    }
+    let file = common::file(&[flight]);
+    let options = DecodeConfig {
+        high_resolution_fuel_flow: true,
+        ..Default::default()
+    };
+    let flights = file.flights_with(&options).unwrap();
+    let records = &flights[0].records;
+    assert_eq!(records[0].fuel_flow, Some(2.4));
+    assert_eq!(records[599].fuel_flow, Some(9.8));
+
+    let expected = records
+        .iter()
+        .map(|r| r.fuel_flow.unwrap() * 6.0 / 3600.0)
+        .sum::<f64>();
+    let burned = flights[0].stats().fuel_used.unwrap();
+    assert!((burned - expected).abs() < 1e-9);
+    assert!((9.0..9.8).contains(&burned));
+}
+
+#[test]
+fn high_resolution_fuel_flow_fixture() {
+    // Two minutes at 6 seconds a record: 2.40 gph rising by 2.00 a record to
+    // 12.34 gph from the sixth record on, for 145110 hundredths in all
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/ff_hundredths.jpi"
+    );
+    let header = EdmHeader::new(path).unwrap();
+    let options = DecodeConfig {
+        high_resolution_fuel_flow: true,
+        ..Default::default()
+    };
+    let flights = header.flights_with(&options).unwrap();
+    let records = &flights[0].records;
+    assert_eq!(records.len(), 120);
+    assert_eq!(records[0].fuel_flow, Some(2.4));
+    assert_eq!(records[1].fuel_flow, Some(4.4));
+    assert_eq!(records[119].fuel_flow, Some(12.34));
+    let burned = flights[0].stats().fuel_used.unwrap();
+    assert!((burned - 1451.1 * 6.0 / 3600.0).abs() < 1e-9);
+
+    // Read as tenths the same file burns ten times as much
+    let tenths = header.flights().unwrap()[0].stats().fuel_used.unwrap();
+    assert!((tenths - burned * 10.0).abs() < 1e-9);
+}
+
+#[test]
+fn twin_tit_install() {
+    // Six cylinders with a TIT probe on each turbocharger, as fitted to a
+    // turbo-normalized twin. Each TIT has its own field after the EGTs, so
+    // the second probe must not push the EGTs along.
+    const TWIN_TIT: u32 = 1 | 0x3f << 2 | 0x3f << 11 | 1 << 20 | 1 << 21 | 1 << 22;
+    let mut flight = common::flight(1, 3);
+    let header = flight.header.as_mut().unwrap();
+    header.flags = TWIN_TIT;
+    header.sensors = Sensors::from_flags(TWIN_TIT);
+    for record in &mut flight.records {
+        *record = Record {
+            timestamp: record.timestamp,
+            egt: vec![1350, 1360, 1370, 1380, 1390, 1400],
+            cht: vec![340, 345, 350, 355, 360, 365],
+            tit1: Some(1450),
+            tit2: Some(1480),
+            oil_temp: Some(190),
+            volts: Some(28.1),
+            ..Default::default()
+        };
+    }
+    let mut lines = common::header_lines();
+    lines[2] = format!(
+        "C,900,{},{},1048,8418,120,140,2011,10",
+        TWIN_TIT & 0xffff,
+        TWIN_TIT >> 16
+    );
+    let flights = common::file_with(&lines, &[flight]).flights().unwrap();
+    assert_eq!(flights[0].diagnostics, []);
+
+    let record = &flights[0].records[2];
+    assert_eq!(record.egt, [1350, 1360, 1370, 1380, 1390, 1400]);
+    assert_eq!(record.tit1, Some(1450));
+    assert_eq!(record.tit2, Some(1480));
+    assert_eq!(record.cht, [340, 345, 350, 355, 360, 365]);
+
+    let mut csv = Vec::new();
+    flights[0].write_csv(&mut csv, CsvDialect::Native).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("TIME,EGT1,EGT2,EGT3,EGT4,EGT5,EGT6,TIT1,TIT2,CHT1,CHT2,CHT3,CHT4,CHT5,CHT6,OIL,VOLTS,MARK")
+    );
+    assert!(lines
+        .next()
+        .unwrap()
+        .ends_with(",1350,1360,1370,1380,1390,1400,1450,1480,340,345,350,355,360,365,190,28.1,0"));
+}
+
+#[test]
+fn channel_series() {
+    let flights = common::file(&[common::flight(1, 8)]).flights().unwrap();
+    let series = flights[0].channel_series("EGT1").unwrap();
+    assert_eq!(series.len(), flights[0].records.len());
+    assert_eq!(series[..3], [(0.0, 1300.0), (6.0, 1301.0), (12.0, 1302.0)]);
+    assert_eq!(flights[0].channel_series("ff").unwrap()[7], (42.0, 9.8));
+
+    assert_eq!(flights[0].channel_series("TIT1"), None);
+    assert_eq!(flights[0].channel_series("EGT9"), None);
+    assert_eq!(flights[0].channel_series("DIF"), None);
+}
+
+/// Field positions that decode to a reading, with the channel they hold.
+fn observed_fields() -> Vec<(usize, Channel)> {
+    let mut fields = (0..6u8)
+        .map(|n| (n as usize, Channel::Egt(n + 1)))
+        .chain((0..6u8).map(|n| (8 + n as usize, Channel::Cht(n + 1))))
+        .collect::<Vec<(usize, Channel)>>();
+    fields.extend([
+        (6, Channel::Tit1),
+        (7, Channel::Tit2),
+        (15, Channel::OilTemp),
+        (17, Channel::OilPressure),
+        (18, Channel::Cdt),
+        (19, Channel::Iat),
+        (20, Channel::Volts),
+        (21, Channel::Oat),
+        (22, Channel::FuelUsed),
+        (23, Channel::FuelFlow),
+        (40, Channel::Map),
+        (41, Channel::Rpm),
+    ]);
+    fields
+}
+
+/// A raw record setting every field of the field flag bytes `bits`
+/// announce, field `i` of each byte by `i + 1`, negated where `signs` has
+/// bit `i` set. `high` adds a high byte of 1 to fields 0-7.
+fn raw_record(bits: &[u16], signs: u8, high: bool) -> Vec<u8> {
+    let mut decode_flags = bits.iter().fold(0u16, |flags, bit| flags | 1 << bit);
+    if high {
+        decode_flags |= 1 << 6;
+    }
+    let mut record = [decode_flags.to_be_bytes(), decode_flags.to_be_bytes()].concat();
+    record.push(0);
+    let set = (0..16).filter(|bit| decode_flags >> bit & 1 == 1).count();
+    record.extend(vec![0xff; set]);
+    let sign_bytes = bits.iter().filter(|bit| !(6..8).contains(*bit)).count();
+    record.extend(vec![signs; sign_bytes]);
+    for _ in 0..set {
+        record.extend(1..=8u8);
+    }
+    if high {
+        // The high bytes follow the low bytes of field flag byte 0
+        let at = record.len() - 8;
+        record[at..].fill(1);
+    }
+    record.push(0u8.wrapping_sub(record.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))));
+    record
+}
+
+#[test]
+fn every_decode_flag_bit() {
+    // Every channel on, six cylinders
+    const ALL: u32 = 1 | 0x3f << 2 | 0x3f << 11 | 0x1ff << 20 | 1 << 30;
+    let header = FlightHeader {
+        number: 1,
+        flags: ALL,
+        sensors: Sensors::from_flags(ALL),
+        interval_secs: 6,
+        start_time: Some(1_700_000_000),
+    };
+    let mut lines = common::header_lines();
+    lines[2] = format!(
+        "C,900,{},{},1048,8418,120,140,2011,10",
+        ALL & 0xffff,
+        ALL >> 16
+    );
+
+    // Field flag bytes, each with the decode flag bit announcing it
+    let bytes = (0..6u16).chain(8..16).collect::<Vec<u16>>();
+    let mut block = header.encode(true);
+    for bit in &bytes {
+        // Odd fields go down, then a second record brings every field back
+        block.extend(raw_record(&[*bit], 0b1010_1010, false));
+        block.extend(raw_record(&[*bit], 0b0101_0101, false));
+    }
+    block.extend(raw_record(&[0], 0b1010_1010, true));
+    block.extend(raw_record(&[0], 0b0101_0101, true));
+    // High bytes for fields 24-31, which hold no channel
+    block.extend(raw_record(&[3, 7], 0, false));
+
+    let flights = common::file_with_blocks(&lines, &[(1, block)])
+        .flights()
+        .unwrap();
+    let records = &flights[0].records;
+    assert!(!flights[0]
+        .diagnostics
+        .iter()
+        .any(|d| matches!(d, Diagnostic::BadRecord { .. })));
+    assert_eq!(records.len(), bytes.len() * 2 + 3);
+
+    let raw = |record: &Record, channel: Channel| {
+        (record.get(channel).unwrap() * 10f64.powi(channel.decimals() as i32)).round() as i32
+    };
+    let expected = |field: usize, byte: usize, high: bool| {
+        if field / 8 != byte {
+            return 0xf0;
+        }
+        let i = field % 8;
+        let mut delta = i as i32 + 1 + if high { 256 } else { 0 };
+        if field == 41 {
+            // RPM takes the next field as its high byte
+            delta += 3 << 8;
+        }
+        if i % 2 == 1 {
+            0xf0 - delta
+        } else {
+            0xf0 + delta
+        }
+    };
+    let pairs = bytes
+        .iter()
+        .map(|bit| (if *bit < 6 { *bit } else { bit - 2 } as usize, false))
+        .chain([(0, true)]);
+    for (n, (byte, high)) in pairs.enumerate() {
+        let (moved, restored) = (&records[n * 2], &records[n * 2 + 1]);
+        for (field, channel) in observed_fields() {
+            assert_eq!(
+                raw(moved, channel),
+                expected(field, byte, high),
+                "field {field} from field flag byte {byte}"
+            );
+            assert_eq!(raw(restored, channel), 0xf0, "field {field}");
+        }
+        let mark = if byte == 2 { 0xf1 } else { 0xf0 };
+        assert_eq!(moved.mark, mark);
+    }
+    assert_eq!(records.last().unwrap().get(Channel::Egt(1)), Some(240.0));
+}
+
+#[test]
+fn ground_idle_repeats() {
+    // RPM alone, as field 41 of field flag byte 5
+    let record = |repeat: u8, delta: u8| {
+        let mut record = vec![0, 1 << 5, 0, 1 << 5, repeat, 0b010, 0, delta];
+        record.push(0u8.wrapping_sub(record.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))));
+        record
+    };
+    let header = common::flight(1, 0).header.unwrap();
+    let mut idle = header.encode(true);
+    idle.extend(record(0, 200));
+    // The 440 RPM record held for four more samples before this one
+    idle.extend(record(4, 10));
+    idle.extend(record(0, 10));
+    let mut leading = header.encode(true);
+    leading.extend(record(3, 200));
+    leading.extend(record(0, 10));
+
+    let file = common::file_with_blocks(&common::header_lines(), &[(1, idle), (2, leading)]);
+    let flights = file.flights().unwrap();
+    let rpm = flights[0]
+        .records
+        .iter()
+        .map(|r| r.rpm.unwrap())
+        .collect::<Vec<i32>>();
+    assert_eq!(rpm, [440, 440, 440, 440, 440, 450, 460]);
+    let start = header.start_time.unwrap();
+    for (i, record) in flights[0].records.iter().enumerate() {
+        assert_eq!(record.timestamp, Some(start + 6 * i as i64));
+    }
+    assert_eq!(flights[0].diagnostics, []);
+    assert_eq!(file.skim_flights().unwrap()[0].sample_count(), 7);
+
+    assert_eq!(flights[1].records.len(), 2);
+    assert_eq!(
+        flights[1].diagnostics,
+        [Diagnostic::LeadingRepeat {
+            flight: 2,
+            count: 3
+        }]
+    );
+}
+
+#[test]
+fn rpm_falls_across_a_high_byte() {
+    // Field flag byte 5 holds MAP, RPM and RPM's high byte as fields 40-42
+    let record = |fields: u8, signs: u8, deltas: &[u8]| {
+        let mut record = vec![0, 1 << 5, 0, 1 << 5, 0, fields, signs];
+        record.extend(deltas);
+        record.push(0u8.wrapping_sub(record.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))));
+        record
+    };
+    let flight = common::flight(1, 0);
+    let mut block = flight.header.unwrap().encode(true);
+    // Up 2360 RPM from the initial 240 as 9 << 8 + 56, MAP up 5.5
+    block.extend(record(0b111, 0, &[55, 56, 9]));
+    // Down 300 RPM as 1 << 8 + 44 with the sign on RPM alone, MAP down 14.5
+    block.extend(record(0b111, 0b011, &[145, 44, 1]));
+    // Down 1 RPM with no high byte
+    block.extend(record(0b010, 0b010, &[1]));
+
+    let flights = common::file_with_blocks(&common::header_lines(), &[(1, block)])
+        .flights()
+        .unwrap();
+    let records = &flights[0].records;
+    assert_eq!(flights[0].diagnostics, []);
+    let rpm = records.iter().map(|r| r.rpm).collect::<Vec<Option<i32>>>();
+    assert_eq!(rpm, [Some(2600), Some(2300), Some(2299)]);
+    let map = records.iter().map(|r| r.map).collect::<Vec<Option<f64>>>();
+    assert_eq!(map, [Some(29.5), Some(15.0), Some(15.0)]);
+}
+
+#[test]
+fn skimmed_sample_count() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/4cyl.jpi");
+    let header = EdmHeader::new(path).unwrap();
+    let skimmed = header.skim_flights().unwrap();
+    let decoded = header.flights().unwrap();
+    assert!(skimmed.iter().all(|f| f.records.is_empty()));
+    for (skimmed, decoded) in skimmed.iter().zip(&decoded) {
+        assert_eq!(skimmed.offset, decoded.offset);
+        assert_eq!(skimmed.sample_count(), decoded.records.len());
+        assert_eq!(skimmed.duration(), decoded.duration());
+        // The summary agrees on how long the flight ran
+        assert_eq!(
+            Some(decoded.stats().duration_secs as u64),
+            decoded.duration().map(|d| d.as_secs())
+        );
+    }
+    assert_eq!(skimmed[0].sample_count(), 16);
+    assert_eq!(skimmed[0].duration(), Some(Duration::from_secs(90)));
+}
+
+#[test]
+fn every_flight_of_a_real_download() {
+    // The first flight is 2899 bytes, an odd length `$D` rounds up to 1450
+    // words, and the next flight starts straight after its last record
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/FILE.JPI");
+    let header = EdmHeader::new(path).unwrap();
+    let flights = header.flights().unwrap();
+    assert_eq!(flights.len(), 21);
+    assert_eq!(flights[1].offset, flights[0].offset + 2899);
+    for flight in &flights {
+        assert!(!flight.records.is_empty(), "flight {}", flight.number);
+        assert_eq!(flight.diagnostics, [], "flight {}", flight.number);
+    }
+}
+
+#[test]
+fn fixture_self_test() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/4cyl.jpi");
+    let header = EdmHeader::new(path).unwrap();
+    let result = header.self_test(3).unwrap().unwrap();
+    assert_eq!(result.flight, 3);
+    assert_eq!(result.records, 16);
+    assert!(result.values_match);
+    assert!(header.self_test(99).unwrap().is_none());
+
+    // A real download decodes back to the same values, but the EDM writes
+    // fields the encoder does not, from the very first record
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/FILE.JPI");
+    let header = EdmHeader::new(path).unwrap();
+    let result = header.self_test(1).unwrap().unwrap();
+    assert_eq!(result.records, 81);
+    assert!(result.values_match);
+    assert!(!result.bytes_match);
+    assert_eq!(result.first_difference, Some(0));
+
+    // A flight that stopped at a bad record is an error, not a missing flight
+    let mut block = common::flight(1, 6).encode(true).unwrap();
+    let last = block.len() - 1;
+    block[last] ^= 0xff;
+    let file = common::file_with_blocks(&common::header_lines(), &[(1, block)]);
+    assert!(matches!(
+        file.flights().unwrap()[0].diagnostics[..],
+        [Diagnostic::BadRecord { .. }]
+    ));
+    assert_eq!(
+        file.self_test(1).unwrap_err(),
+        ParseError::SelfTestFlight { flight: 1 }
+    );
+}
+
+#[test]
+fn trailing_padding() {
+    let flight = common::flight(1, 6);
+    let mut block = flight.encode(true).unwrap();
+    block.extend([0; 24]);
+    let padded = common::file_with_blocks(&common::header_lines(), &[(1, block)]);
+
+    let flights = padded.flights().unwrap();
+    assert_eq!(flights[0].records.len(), 6);
+    assert_eq!(flights[0].diagnostics, []);
+    assert_eq!(flights[0].records[5].rpm, Some(2405));
+    assert_eq!(padded.skim_flights().unwrap()[0].sample_count(), 6);
+
+    // A record that repeats the last one exactly is still decoded
+    let mut flight = common::flight(1, 6);
+    flight.records[5] = flight.records[4].clone();
+    flight.records[5].timestamp = flight.records[4].timestamp.map(|t| t + 6);
+    let flights = common::file(&[flight]).flights().unwrap();
+    assert_eq!(flights[0].records.len(), 6);
+    assert_eq!(flights[0].records[5].rpm, Some(2404));
+}
+
+#[test]
+fn flight_longer_than_16_bit_word_count() {
+    // `$D` counts are decimal text, so there is no field width to overflow
+    let file = common::file(&[common::flight(1, 20_000), common::flight(2, 3)]);
+    let index = file.data().flights;
+    assert!(index[0].words > usize::from(u16::MAX));
+
+    let flights = file.flights().unwrap();
+    assert_eq!(flights[0].records.len(), 20_000);
+    assert_eq!(flights[0].diagnostics, []);
+    assert_eq!(flights[1].offset, flights[0].offset + flights[0].words * 2);
+    assert_eq!(flights[1].records.len(), 3);
+    assert_eq!(flights[1].diagnostics, []);
+}
+
+#[test]
+fn truncated_final_flight() {
+    let blocks = [common::flight(1, 10), common::flight(2, 40)]
+        .iter()
+        .map(|flight| (flight.number, flight.encode(true).unwrap()))
+        .collect::<Vec<(i32, Vec<u8>)>>();
+    let mut bytes = common::file_bytes(&common::header_lines(), &blocks, &[]);
+    let full = EdmHeader::from_bytes(bytes.clone()).flights().unwrap();
+    // The download stopped partway through the second flight, before `$E`
+    bytes.truncate(bytes.len() - 9 - 100);
+
+    let flights = EdmHeader::from_bytes(bytes).flights().unwrap();
+    assert_eq!(flights[0], full[0]);
+    let last = &flights[1];
+    assert_eq!(
+        last.diagnostics[0],
+        Diagnostic::TruncatedFlight {
+            flight: 2,
+            expected_bytes: last.words * 2,
+            found_bytes: blocks[1].1.len() - 100,
+        }
+    );
+    assert!(!last.records.is_empty());
+    assert!(last.records.len() < full[1].records.len());
+    assert_eq!(last.records[..], full[1].records[..last.records.len()]);
+}
+
+#[test]
+fn binary_preamble() {
+    let blocks = [common::flight(3, 8), common::flight(4, 5)]
+        .iter()
+        .map(|flight| (flight.number, flight.encode(true).unwrap()))
+        .collect::<Vec<(i32, Vec<u8>)>>();
+    let lines = common::header_lines();
+    let plain = EdmHeader::from_bytes(common::file_bytes(&lines, &blocks, &[]));
+    let preamble = [0x4a, 0x50, 0x49, 0x00, 0x03, 0x00, 0x07];
+    let file = EdmHeader::from_bytes(common::file_bytes(&lines, &blocks, &preamble));
+
+    let expected = plain.flights().unwrap();
+    let flights = file.flights().unwrap();
+    assert_eq!(flights.len(), 2);
+    for (flight, expected) in flights.iter().zip(&expected) {
+        assert_eq!(flight.offset, expected.offset + preamble.len());
+        assert_eq!(flight.diagnostics, []);
+        assert_eq!(
+            format!("{:?}", flight.records),
+            format!("{:?}", expected.records)
+        );
+    }
+    let skimmed = file.skim_flights().unwrap();
+    assert_eq!(skimmed[0].offset, flights[0].offset);
+    assert_eq!(skimmed[1].sample_count(), 5);
+}
+
+#[test]
+fn first_records() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/4cyl.jpi");
+    let header = EdmHeader::new(path).unwrap();
+    let first = header.first_records().unwrap();
+    let flights = header.flights().unwrap();
+    assert_eq!(first.len(), flights.len());
+    for ((number, first), flight) in first.iter().zip(&flights) {
+        assert_eq!(*number, flight.number);
+        assert_eq!(format!("{first:?}"), format!("{:?}", flight.records[0]));
+    }
+    assert_eq!(first[0].1.egt, [1154, 1106, 1153, 1127]);
+    assert_eq!(first[1].1.oil_temp, Some(76));
+
+    // Every flight's first record, however many flights are in the file
+    let flights = [
+        common::flight(1, 8),
+        common::flight(2, 5),
+        common::flight(3, 12),
+    ];
+    let file = common::file(&flights);
+    let first = file.first_records().unwrap();
+    let decoded = file.flights().unwrap();
+    assert_eq!(first.len(), 3);
+    for ((number, first), flight) in first.iter().zip(&decoded) {
+        assert_eq!(*number, flight.number);
+        assert_eq!(first, &flight.records[0]);
+    }
+
+    let lines = common::header_lines()
+        .into_iter()
+        .filter(|line| !line.starts_with('C'))
+        .collect::<Vec<String>>();
+    let file = common::file_with(&lines, &flights);
+    assert_eq!(file.first_records(), Err(ParseError::MissingConfig));
+}
+
+#[test]
+fn skim_after_an_odd_length_flight() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/FILE.JPI");
+    let header = EdmHeader::new(path).unwrap();
+    let flights = header.flights().unwrap();
+    let skimmed = header.skim_flights().unwrap();
+    assert_eq!(skimmed.len(), 21);
+    for (skimmed, flight) in skimmed.iter().zip(&flights) {
+        assert_eq!(skimmed.offset, flight.offset);
+        assert_eq!(skimmed.header, flight.header);
+        assert_eq!(skimmed.sample_count(), flight.records.len());
+    }
+    let first = header.first_records().unwrap();
+    assert_eq!(
+        first
+            .iter()
+            .map(|(number, _)| *number)
+            .collect::<Vec<i32>>(),
+        (1..=21).collect::<Vec<i32>>()
+    );
+    for ((_, first), flight) in first.iter().zip(&flights) {
+        assert_eq!(first, &flight.records[0]);
+    }
+}
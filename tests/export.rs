@@ -0,0 +1,659 @@
+mod common;
+
+use std::collections::HashMap;
+
+use jpi::{
+    check_aliases, csv_escape, merge_flights, write_flights_csv, Channel, CsvDialect, DecodeConfig,
+    ExportOptions, Flight, ImportError, Json, ParseError, Record, Sensors,
+};
+
+fn export(options: &ExportOptions) -> Vec<String> {
+    let flights = common::file(&[common::flight(1, 5)]).flights().unwrap();
+    let mut csv = Vec::new();
+    flights[0].write_csv_with(&mut csv, options).unwrap();
+    String::from_utf8(csv)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn elapsed_time() {
+    let rows = export(&ExportOptions {
+        elapsed_time: true,
+        ..Default::default()
+    });
+    let times = rows[1..]
+        .iter()
+        .map(|row| row.split(',').next().unwrap())
+        .collect::<Vec<&str>>();
+    assert_eq!(times, ["0", "6", "12", "18", "24"]);
+
+    let rows = export(&ExportOptions::default());
+    assert!(rows[1].starts_with("2023-11-14 22:13:20,"));
+}
+
+#[test]
+fn record_row() {
+    let flights = common::file(&[common::flight(1, 5)]).flights().unwrap();
+    let record = &flights[0].records[1];
+    let labels = ["EGT1", "volts", "FF", "USD", "EGT9", "MARK", "DIF"];
+    let row = record.to_row(&labels);
+    assert_eq!(row.len(), labels.len());
+    assert_eq!(row, ["1301", "14.2", "9.8", "0.0", "", "0", ""]);
+
+    // The native CSV writes the same strings
+    let mut labels = flights[0].header.as_ref().unwrap().sensors.field_labels();
+    labels.push("MARK".to_string());
+    let labels = labels.iter().map(String::as_str).collect::<Vec<&str>>();
+    let rows = export(&ExportOptions::default());
+    assert_eq!(rows[0].split(',').skip(1).collect::<Vec<&str>>(), labels);
+    assert_eq!(
+        rows[2].split(',').skip(1).collect::<Vec<&str>>(),
+        record.to_row(&labels)
+    );
+    for options in [
+        ExportOptions {
+            si_units: true,
+            ..Default::default()
+        },
+        ExportOptions {
+            raw_values: true,
+            ..Default::default()
+        },
+    ] {
+        let rows = export(&options);
+        assert_eq!(
+            rows[2].split(',').skip(1).collect::<Vec<&str>>(),
+            record.to_row_with(&labels, &options)
+        );
+    }
+    let raw = ExportOptions {
+        raw_values: true,
+        ..Default::default()
+    };
+    assert_eq!(record.to_row_with(&["FF", "MARK"], &raw), ["98", "0"]);
+}
+
+#[test]
+fn si_units() {
+    let rows = export(&ExportOptions {
+        si_units: true,
+        ..Default::default()
+    });
+    assert_eq!(
+        rows[0],
+        "TIME,EGT1,EGT2,EGT3,EGT4,CHT1,CHT2,CHT3,CHT4,OIL,VOLTS,OAT,USD,FF,MAP,RPM,MARK"
+    );
+    // 1301°F, 350°F, 185°F, 14.2 V, 50°F, 9.8 GPH, 23.5 inHg, 2401 RPM
+    let row = rows[2].split(',').collect::<Vec<&str>>();
+    assert_eq!(row[1], "705.0");
+    assert_eq!(row[5], "176.7");
+    assert_eq!(row[9], "85.0");
+    assert_eq!(row[10], "14.2");
+    assert_eq!(row[11], "10.0");
+    assert_eq!(row[13], "37.1");
+    assert_eq!(row[14], "79.6");
+    assert_eq!(row[15], "2401");
+}
+
+#[test]
+fn dedupe_identical_records() {
+    let mut flight = common::flight(1, 8);
+    // Records 2 to 5 read the same apart from their time
+    for i in 3..6 {
+        let timestamp = flight.records[i].timestamp;
+        flight.records[i] = flight.records[2].clone();
+        flight.records[i].timestamp = timestamp;
+    }
+    let flights = common::file(&[flight]).flights().unwrap();
+    let csv = |options: &ExportOptions| {
+        let mut csv = Vec::new();
+        flights[0].write_csv_with(&mut csv, options).unwrap();
+        String::from_utf8(csv).unwrap()
+    };
+    let options = ExportOptions {
+        dedupe: true,
+        ..Default::default()
+    };
+    let deduped = csv(&options);
+    let rows = deduped.lines().collect::<Vec<&str>>();
+    assert!(rows[0].ends_with(",MARK,COUNT"));
+    assert_eq!(rows.len(), 1 + 5);
+    let counts = rows[1..]
+        .iter()
+        .map(|row| row.rsplit(',').next().unwrap())
+        .collect::<Vec<&str>>();
+    assert_eq!(counts, ["1", "1", "4", "1", "1"]);
+    assert!(rows[3].starts_with("2023-11-14 22:13:32,"));
+    assert!(rows[4].starts_with("2023-11-14 22:13:56,"));
+
+    // The EzTrends layout collapses the same run, keeping its first index
+    let rows = csv(&ExportOptions {
+        dialect: CsvDialect::EzTrends,
+        ..options
+    });
+    let rows = rows.lines().collect::<Vec<&str>>();
+    assert_eq!(rows.len(), 1 + 5);
+    assert!(rows[4].starts_with("6,"));
+    assert!(!csv(&ExportOptions::default()).contains("COUNT"));
+}
+
+#[test]
+fn raw_values() {
+    let rows = export(&ExportOptions {
+        raw_values: true,
+        si_units: true,
+        ..Default::default()
+    });
+    assert_eq!(
+        rows[0],
+        "TIME,EGT1,EGT2,EGT3,EGT4,CHT1,CHT2,CHT3,CHT4,OIL,VOLTS,OAT,USD,FF,MAP,RPM,MARK"
+    );
+    // 14.2 V, 9.8 GPH and 23.5 inHg are stored in tenths
+    let row = rows[2].split(',').collect::<Vec<&str>>();
+    assert_eq!(row[1], "1301");
+    assert_eq!(row[10], "142");
+    assert_eq!(row[13], "98");
+    assert_eq!(row[14], "235");
+    assert_eq!(row[15], "2401");
+
+    // Read as hundredths, the stored 98 is 0.98 GPH and still raw 98
+    let decode = DecodeConfig {
+        high_resolution_fuel_flow: true,
+        ..Default::default()
+    };
+    let flights = common::file(&[common::flight(1, 5)])
+        .flights_with(&decode)
+        .unwrap();
+    assert_eq!(flights[0].records[2].fuel_flow, Some(0.98));
+    assert_eq!(
+        flights[0].records[2].raw(Channel::FuelFlow, &decode),
+        Some(98)
+    );
+    let mut csv = Vec::new();
+    let options = ExportOptions {
+        raw_values: true,
+        decode,
+        ..Default::default()
+    };
+    flights[0].write_csv_with(&mut csv, &options).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(csv.lines().nth(2).unwrap().split(',').nth(13), Some("98"));
+}
+
+#[test]
+fn hundredths_fuel_flow_csv() {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/ff_hundredths.jpi"
+    );
+    let decode = DecodeConfig {
+        high_resolution_fuel_flow: true,
+        ..Default::default()
+    };
+    let flights = jpi::EdmHeader::new(path)
+        .unwrap()
+        .flights_with(&decode)
+        .unwrap();
+    let options = ExportOptions {
+        decode,
+        ..Default::default()
+    };
+    let mut csv = Vec::new();
+    flights[0].write_csv_with(&mut csv, &options).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let rows = csv.lines().collect::<Vec<&str>>();
+    let ff = rows[0].split(',').position(|name| name == "FF").unwrap();
+    let column = |row: usize| rows[row].split(',').nth(ff).unwrap();
+    assert_eq!(column(1), "2.40");
+    assert_eq!(column(2), "4.40");
+    assert_eq!(column(120), "12.34");
+}
+
+#[test]
+fn all_flights_csv() {
+    // The middle flight only recorded EGT, CHT and volts
+    const SPARSE: u32 = 1 | 0x3c | 0x7800;
+    let mut sparse = common::flight(2, 3);
+    let header = sparse.header.as_mut().unwrap();
+    header.flags = SPARSE;
+    header.sensors = Sensors::from_flags(SPARSE);
+    for record in &mut sparse.records {
+        *record = Record {
+            timestamp: record.timestamp,
+            egt: record.egt.clone(),
+            cht: record.cht.clone(),
+            volts: record.volts,
+            ..Default::default()
+        };
+    }
+    let file = common::file(&[sparse, common::flight(1, 4), common::flight(3, 6)]);
+    let flights = file.flights().unwrap();
+    let mut csv = Vec::new();
+    write_flights_csv(&flights, &mut csv, &ExportOptions::default()).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let rows = csv.lines().collect::<Vec<&str>>();
+
+    let records = flights.iter().map(|f| f.records.len()).sum::<usize>();
+    assert_eq!(rows.len(), records + 1);
+    assert_eq!(
+        rows[0],
+        "FLIGHT,TIME,EGT1,EGT2,EGT3,EGT4,CHT1,CHT2,CHT3,CHT4,OIL,VOLTS,OAT,USD,FF,MAP,RPM,MARK"
+    );
+    assert!(rows[1].starts_with("2,2023-11-14 22:13:20,1300,"));
+    assert!(rows[1].ends_with(",,14.2,,,,,,0"));
+    assert!(rows[4].starts_with("1,2023-11-14 22:13:20,1300,"));
+    assert!(rows[8].starts_with("3,"));
+    assert!(rows.iter().all(|row| row.split(',').count() == 18));
+}
+
+#[test]
+fn summary_json() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/4cyl.jpi");
+    let header = jpi::EdmHeader::new(path).unwrap();
+    let summary = header.summary_json().unwrap();
+    let flights = header.flights().unwrap();
+
+    let header_json = summary.get("header").unwrap();
+    assert_eq!(
+        header_json.get("registration"),
+        Some(&Json::String("N75278".to_string()))
+    );
+    assert_eq!(
+        header_json.get("model"),
+        Some(&Json::String("EDM-900".to_string()))
+    );
+    let Some(Json::Array(summaries)) = summary.get("flights") else {
+        panic!("no flights in {summary}");
+    };
+    assert_eq!(summaries.len(), flights.len());
+    for (summary, flight) in summaries.iter().zip(&flights) {
+        let stats = flight.stats();
+        assert_eq!(summary.get("number"), Some(&Json::from(flight.number)));
+        assert_eq!(summary.get("records"), Some(&Json::from(stats.records)));
+        assert_eq!(summary.get("max_cht"), Some(&Json::from(stats.max_cht)));
+    }
+
+    // A header line the lenient flight decode skips still fails the summary
+    let bytes = std::fs::read(path).unwrap();
+    let at = bytes.windows(5).position(|w| w == b"$A, 1").unwrap();
+    let bytes = [&bytes[..at], b"$A, HOT*00\r\n", &bytes[at + 53..]].concat();
+    let header = jpi::EdmHeader::from_bytes(bytes);
+    assert_eq!(header.flights().unwrap().len(), flights.len());
+    assert_eq!(
+        header.summary_json().unwrap_err(),
+        ParseError::FieldCount {
+            tag: 'A',
+            line: 2,
+            expected: 8,
+            found: 1
+        }
+    );
+}
+
+#[test]
+fn channel_aliases() {
+    let aliases = HashMap::from([(Channel::Egt(1), "CylFront".to_string())]);
+    let rows = export(&ExportOptions {
+        aliases: aliases.clone(),
+        ..Default::default()
+    });
+    assert!(rows[0].starts_with("TIME,CylFront,EGT2,"));
+    let rows = export(&ExportOptions {
+        dialect: CsvDialect::EzTrends,
+        aliases: aliases.clone(),
+        ..Default::default()
+    });
+    assert!(rows[0].starts_with("INDEX,DATE,TIME,CylFront,E2,"));
+
+    let flights = common::file(&[common::flight(1, 2)]).flights().unwrap();
+    let options = ExportOptions {
+        aliases: aliases.clone(),
+        ..Default::default()
+    };
+    let Some(Json::Array(records)) = flights[0].to_json_with(&options).get("records").cloned()
+    else {
+        panic!("no records");
+    };
+    assert_eq!(records[0].get("CylFront"), Some(&Json::Number(1300.0)));
+    assert_eq!(records[0].get("EGT1"), None);
+
+    // Swapping two labels is fine, reusing one is not
+    let swapped = HashMap::from([
+        (Channel::Egt(1), "EGT2".to_string()),
+        (Channel::Egt(2), "EGT1".to_string()),
+    ]);
+    assert_eq!(check_aliases(&swapped), Ok(()));
+    for alias in ["EGT2", "mark", "CylFront"] {
+        let mut clash = aliases.clone();
+        clash.insert(Channel::Cht(1), alias.to_string());
+        assert!(check_aliases(&clash).is_err(), "{alias}");
+    }
+    let mut csv = Vec::new();
+    let options = ExportOptions {
+        aliases: HashMap::from([(Channel::Volts, "TIME".to_string())]),
+        ..Default::default()
+    };
+    let error = flights[0].write_csv_with(&mut csv, &options).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "alias \"TIME\" for VOLTS is already the name of another column"
+    );
+}
+
+#[test]
+fn fill_gaps_on_a_grid() {
+    let start = 1_700_000_000;
+    // Two halves of one flight 30 seconds apart, the second off the grid
+    let mut second = common::flight_at(1, 3, start + 50);
+    second.records[0].mark = 2;
+    let flights = common::file(&[common::flight_at(1, 4, start), second])
+        .flights()
+        .unwrap();
+    let flight = &merge_flights(flights, 60)[0];
+    let options = ExportOptions {
+        elapsed_time: true,
+        fill_gaps: true,
+        ..Default::default()
+    };
+    let mut csv = Vec::new();
+    flight.write_csv_with(&mut csv, &options).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let rows = csv.lines().skip(1).collect::<Vec<&str>>();
+    let times = rows
+        .iter()
+        .map(|row| row.split(',').next().unwrap().parse::<i64>().unwrap())
+        .collect::<Vec<i64>>();
+    assert_eq!(times, (0..=60).step_by(6).collect::<Vec<i64>>());
+    // 18 is held until the record at 50, which lands on 54 with its mark
+    assert_eq!(rows[4].split(',').nth(1), rows[3].split(',').nth(1));
+    let marks = rows
+        .iter()
+        .map(|row| row.rsplit(',').next().unwrap())
+        .collect::<String>();
+    assert_eq!(marks, "00000000010");
+}
+
+#[test]
+fn csv_quoting() {
+    assert_eq!(csv_escape("1301"), "1301");
+    assert_eq!(csv_escape("N123, Smith"), "\"N123, Smith\"");
+    assert_eq!(csv_escape("the \"big\" one"), "\"the \"\"big\"\" one\"");
+    assert_eq!(csv_escape("two\nlines"), "\"two\nlines\"");
+}
+
+#[test]
+fn flight_columns() {
+    let flights = common::file(&[common::flight(1, 7)]).flights().unwrap();
+    let columns = flights[0].to_columns();
+    let count = flights[0].records.len();
+    assert_eq!(columns.timestamps.len(), count);
+    assert_eq!(columns.marks.len(), count);
+    assert_eq!(
+        columns
+            .channels
+            .keys()
+            .map(Channel::label)
+            .collect::<Vec<String>>(),
+        flights[0].header.as_ref().unwrap().sensors.field_labels()
+    );
+    assert!(columns
+        .channels
+        .values()
+        .all(|values| values.len() == count));
+    assert_eq!(columns.channels[&Channel::Egt(1)][3], Some(1303.0));
+    assert_eq!(columns.channels[&Channel::Volts][3], Some(14.2));
+}
+
+#[test]
+fn flight_across_midnight() {
+    // Starts at 23:58 and records for four minutes
+    let flight = common::flight_at(1, 40, 1_700_006_280);
+    let flights = common::file(&[flight]).flights().unwrap();
+    let timestamps = flights[0]
+        .records
+        .iter()
+        .map(|r| r.timestamp.unwrap())
+        .collect::<Vec<i64>>();
+    assert!(timestamps.windows(2).all(|t| t[1] - t[0] == 6));
+
+    let mut csv = Vec::new();
+    flights[0]
+        .write_csv(&mut csv, CsvDialect::EzTrends)
+        .unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let rows = csv.lines().collect::<Vec<&str>>();
+    assert!(rows[20].starts_with("19,11/14/2023,23:59:54,"));
+    assert!(rows[21].starts_with("20,11/15/2023,00:00:00,"));
+}
+
+#[test]
+fn eztrends_header() {
+    let header = |name: &str, number: i32| {
+        let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+        let flights = jpi::EdmHeader::new(&path).unwrap().flights().unwrap();
+        let flight = flights.iter().find(|f| f.number == number).unwrap();
+        let mut csv = Vec::new();
+        flight.write_csv(&mut csv, CsvDialect::EzTrends).unwrap();
+        String::from_utf8(csv)
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(
+        header("4cyl.jpi", 3),
+        "INDEX,DATE,TIME,E1,E2,E3,E4,C1,C2,C3,C4,OAT,DIF,MAP,RPM,FF,OILP,BAT,OILT,USD,MARK"
+    );
+    assert_eq!(
+        header("6cyl.jpi", 7),
+        "INDEX,DATE,TIME,E1,E2,E3,E4,E5,E6,C1,C2,C3,C4,C5,C6,T1,OAT,DIF,MAP,RPM,FF,BAT,OILT,USD,MARK"
+    );
+}
+
+#[test]
+fn flight_json() {
+    let flights = common::file(&[common::flight(1, 3)]).flights().unwrap();
+    let json = flights[0].to_json();
+    assert_eq!(json.get("number"), Some(&Json::Number(1.0)));
+    let Some(Json::Array(records)) = json.get("records") else {
+        panic!("no records in {json}");
+    };
+    assert_eq!(records.len(), 3);
+    assert_eq!(
+        records[1].to_string(),
+        "{\"time\":1700000006,\"EGT1\":1301,\"EGT2\":1310,\"EGT3\":1291,\"EGT4\":1320,\
+         \"CHT1\":350,\"CHT2\":361,\"CHT3\":355,\"CHT4\":345,\"OIL\":185,\"VOLTS\":14.2,\
+         \"OAT\":50,\"USD\":0,\"FF\":9.8,\"MAP\":23.5,\"RPM\":2401,\"mark\":0}"
+    );
+    assert_eq!(
+        Json::String("say \"hi\"\n".to_string()).to_string(),
+        "\"say \\\"hi\\\"\\n\""
+    );
+}
+
+/// The member names of a JSON object, in order.
+fn keys(object: &Json) -> Vec<&str> {
+    match object {
+        Json::Object(members) => members.iter().map(|(key, _)| key.as_str()).collect(),
+        _ => panic!("not an object: {object}"),
+    }
+}
+
+#[test]
+fn flight_json_schema() {
+    assert_eq!(
+        Flight::json_schema().to_string(),
+        "{\"$schema\":\"https://json-schema.org/draft/2020-12/schema\",\"title\":\"jpi flight\",\
+         \"type\":\"object\",\"properties\":{\"number\":{\"type\":\"integer\"},\
+         \"start_time\":{\"type\":[\"integer\",\"null\"]},\
+         \"interval_secs\":{\"type\":[\"integer\",\"null\"]},\
+         \"diagnostics\":{\"type\":\"array\",\"items\":{\"type\":\"string\"}},\
+         \"records\":{\"type\":\"array\",\"items\":{\"type\":\"object\",\
+         \"properties\":{\"time\":{\"type\":[\"integer\",\"null\"]},\"mark\":{\"type\":\"integer\"}},\
+         \"required\":[\"time\",\"mark\"],\"additionalProperties\":{\"type\":[\"number\",\"null\"]}}}},\
+         \"required\":[\"number\",\"start_time\",\"interval_secs\",\"diagnostics\",\"records\"],\
+         \"additionalProperties\":false}"
+    );
+
+    let schema = Flight::json_schema();
+    let document = common::file(&[common::flight(1, 3)]).flights().unwrap()[0].to_json();
+    assert_eq!(keys(&document), keys(schema.get("properties").unwrap()));
+}
+
+#[test]
+fn summary_json_schema() {
+    let header = "{\"type\":\"object\",\"properties\":{\"registration\":{\"type\":[\"string\",\"null\"]},\
+         \"serial_number\":{\"type\":[\"string\",\"null\"]},\"model\":{\"type\":[\"string\",\"null\"]},\
+         \"firmware_version\":{\"type\":[\"integer\",\"null\"]},\
+         \"download_time\":{\"type\":[\"integer\",\"null\"]},\
+         \"protocol_version\":{\"type\":[\"integer\",\"null\"]},\"flight_count\":{\"type\":\"integer\"}},\
+         \"required\":[\"registration\",\"serial_number\",\"model\",\"firmware_version\",\
+         \"download_time\",\"protocol_version\",\"flight_count\"],\"additionalProperties\":false}";
+    let stats = "{\"type\":\"object\",\"properties\":{\"number\":{\"type\":\"integer\"},\
+         \"records\":{\"type\":\"integer\"},\"start_time\":{\"type\":[\"integer\",\"null\"]},\
+         \"duration_secs\":{\"type\":\"integer\"},\"max_cht\":{\"type\":[\"number\",\"null\"]},\
+         \"fuel_used\":{\"type\":[\"number\",\"null\"]},\
+         \"faulted_channels\":{\"type\":\"array\",\"items\":{\"type\":\"string\"}}},\
+         \"required\":[\"number\",\"records\",\"start_time\",\"duration_secs\",\"max_cht\",\
+         \"fuel_used\",\"faulted_channels\"],\"additionalProperties\":false}";
+    let titled = |title: &str, schema: &str| {
+        format!(
+            "{{\"$schema\":\"https://json-schema.org/draft/2020-12/schema\",\"title\":\"{title}\",{}",
+            &schema[1..]
+        )
+    };
+    assert_eq!(
+        jpi::HeaderData::json_schema().to_string(),
+        titled("jpi header", header)
+    );
+    assert_eq!(
+        jpi::FlightStats::json_schema().to_string(),
+        titled("jpi flight summary", stats)
+    );
+    let summary = format!(
+        "{{\"type\":\"object\",\"properties\":{{\"header\":{header},\
+         \"flights\":{{\"type\":\"array\",\"items\":{stats}}}}},\
+         \"required\":[\"header\",\"flights\"],\"additionalProperties\":false}}"
+    );
+    assert_eq!(
+        jpi::EdmHeader::summary_json_schema().to_string(),
+        titled("jpi file summary", &summary)
+    );
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/4cyl.jpi");
+    let document = jpi::EdmHeader::new(path).unwrap().summary_json().unwrap();
+    let schema = jpi::EdmHeader::summary_json_schema();
+    let properties = |schema: &Json, name: &str| {
+        let property = schema.get("properties").unwrap().get(name).unwrap().clone();
+        match property.get("items") {
+            Some(items) => items.get("properties").unwrap().clone(),
+            None => property.get("properties").unwrap().clone(),
+        }
+    };
+    assert_eq!(keys(&document), ["header", "flights"]);
+    assert_eq!(
+        keys(document.get("header").unwrap()),
+        keys(&properties(&schema, "header"))
+    );
+    let Some(Json::Array(flights)) = document.get("flights") else {
+        panic!("flights is an array");
+    };
+    assert!(!flights.is_empty());
+    for flight in flights {
+        assert_eq!(keys(flight), keys(&properties(&schema, "flights")));
+    }
+}
+
+#[test]
+fn csv_round_trip() {
+    let mut flight = common::flight(1, 12);
+    flight.records[3].mark = 2;
+    let flights = common::file(&[flight]).flights().unwrap();
+    let labels = flights[0].header.as_ref().unwrap().sensors.field_labels();
+    let mut csv = Vec::new();
+    flights[0].write_csv(&mut csv, CsvDialect::Native).unwrap();
+
+    let read = Flight::from_csv(&csv[..], &labels).unwrap();
+    assert_eq!(read.records, flights[0].records);
+    assert!(read.records[3].is_marked());
+
+    // An edited reading comes back as edited
+    let text = String::from_utf8(csv).unwrap();
+    let edited = text.replacen(",2400,", ",2500,", 1);
+    let read = Flight::from_csv(edited.as_bytes(), &labels).unwrap();
+    assert_eq!(read.records[0].rpm, Some(2500));
+
+    let error = |csv: &str, labels: &[String]| {
+        let error = Flight::from_csv(csv.as_bytes(), labels).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        *error
+            .into_inner()
+            .unwrap()
+            .downcast::<ImportError>()
+            .unwrap()
+    };
+    assert!(matches!(
+        error(&text, &labels[1..]),
+        ImportError::HeaderMismatch { .. }
+    ));
+    assert_eq!(
+        error(&text.replacen(",2400,", ",fast,", 1), &labels),
+        ImportError::InvalidCell {
+            row: 1,
+            column: "RPM".to_string(),
+            value: "fast".to_string(),
+        }
+    );
+    assert_eq!(
+        error("TIME,FOO,MARK\n", &["FOO".to_string()]),
+        ImportError::UnknownLabel {
+            label: "FOO".to_string()
+        }
+    );
+}
+
+#[test]
+fn si_json() {
+    let flights = common::file(&[common::flight(1, 3)]).flights().unwrap();
+    let options = ExportOptions {
+        si_units: true,
+        ..Default::default()
+    };
+    let Some(Json::Array(records)) = flights[0].to_json_with(&options).get("records").cloned()
+    else {
+        panic!("no records");
+    };
+    let number = |name: &str| match records[0].get(name) {
+        Some(Json::Number(n)) => *n,
+        other => panic!("{name} is {other:?}"),
+    };
+    assert!((number("EGT1") - 704.444).abs() < 0.001);
+    assert!((number("OAT") - 10.0).abs() < 1e-9);
+    assert!((number("MAP") - 79.58).abs() < 0.01);
+    assert!((number("FF") - 37.097).abs() < 0.001);
+    assert_eq!(number("VOLTS"), 14.2);
+    assert_eq!(number("RPM"), 2400.0);
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/4cyl.jpi");
+    let header = jpi::EdmHeader::new(path).unwrap();
+    let stats = header.flights().unwrap()[0].stats();
+    let summary = header.summary_json_with(true).unwrap();
+    let Some(Json::Array(summaries)) = summary.get("flights") else {
+        panic!("no flights in {summary}");
+    };
+    let max_cht = f64::from(stats.max_cht.unwrap());
+    assert_eq!(
+        summaries[0].get("max_cht"),
+        Some(&Json::Number((max_cht - 32.0) * 5.0 / 9.0))
+    );
+    assert_eq!(
+        summaries[0].get("fuel_used"),
+        Some(&Json::Number(
+            Channel::FuelUsed.to_si(stats.fuel_used.unwrap()).unwrap()
+        ))
+    );
+}
@@ -0,0 +1,67 @@
+use std::{
+    fs,
+    time::{Duration, SystemTime},
+};
+
+use jpi::{EdmHeader, IndexEntry, SidecarIndex};
+
+#[test]
+fn sidecar_round_trip() {
+    let dir = std::env::temp_dir().join(format!("jpi-index-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("4cyl.jpi");
+    fs::copy(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/4cyl.jpi"),
+        &source,
+    )
+    .unwrap();
+
+    let fresh =
+        SidecarIndex::from_file(&EdmHeader::new(source.to_str().unwrap()).unwrap()).unwrap();
+    assert_eq!(fresh.registration.as_deref(), Some("N75278"));
+    assert_eq!(
+        fresh.flights[0],
+        IndexEntry {
+            number: 3,
+            offset: 230,
+            words: 324,
+            start_time: Some(1658268792),
+        }
+    );
+
+    assert_eq!(SidecarIndex::load(&source), None);
+    assert_eq!(SidecarIndex::open(&source).unwrap(), fresh);
+    assert!(dir.join("4cyl.jpi.idx").exists());
+    assert_eq!(SidecarIndex::load(&source), Some(fresh));
+
+    // A source changed after the sidecar was written is indexed again
+    let later = SystemTime::now() + Duration::from_secs(60);
+    fs::File::options()
+        .append(true)
+        .open(&source)
+        .unwrap()
+        .set_modified(later)
+        .unwrap();
+    assert_eq!(SidecarIndex::load(&source), None);
+
+    assert!(SidecarIndex::read(&b"JPIIDX"[..]).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn index_matches_decode() {
+    let header = EdmHeader::new(concat!(env!("CARGO_MANIFEST_DIR"), "/FILE.JPI")).unwrap();
+    let index = SidecarIndex::from_file(&header).unwrap();
+    let flights = header.flights().unwrap();
+    assert_eq!(index.flights.len(), flights.len());
+    for (entry, flight) in index.flights.iter().zip(&flights) {
+        assert_eq!(entry.number, flight.number);
+        assert_eq!(entry.offset, flight.offset as u64);
+        assert_eq!(entry.words, flight.words as u64);
+        assert_eq!(
+            entry.start_time,
+            flight.header.as_ref().and_then(|h| h.start_time)
+        );
+    }
+}
@@ -0,0 +1,692 @@
+//! Parses the files in `tests/fixtures`.
+//!
+//! `4cyl.jpi` is an EDM-900 download from a 4 cylinder engine, cut down to
+//! two of its flights. `6cyl.jpi` was written with `Flight::encode` for a 6
+//! cylinder engine with a TIT probe. `serial.jpi` is `4cyl.jpi` with a
+//! serial number in `$H`, and `bad_checksum.jpi` is `4cyl.jpi` with the
+//! `$U` checksum changed. `ff_hundredths.jpi` was written with
+//! `Flight::encode` for an EDM-900 recording fuel flow in hundredths.
+//! `odd_flight.jpi` is the first two flights of the same download as
+//! `4cyl.jpi`, the first of them an odd number of bytes long.
+
+mod common;
+
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use jpi::{
+    Channel, DecodeConfig, Diagnostic, EdmHeader, Features, FieldChange, HeaderData, ParseError,
+};
+
+fn fixture(name: &str) -> EdmHeader {
+    let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    EdmHeader::new(&path).expect("Could not open fixture")
+}
+
+#[test]
+fn four_cylinder_header() {
+    let data = fixture("4cyl.jpi").data();
+    assert_eq!(data.registration.as_deref(), Some("N75278"));
+    assert_eq!(data.serial_number, None);
+    assert_eq!(data.protocol_version, Some(2));
+    assert!(data.diagnostics.is_empty());
+
+    let alarms = data.alarms.unwrap();
+    assert_eq!(alarms.max_volts, Some(15.0));
+    assert_eq!(alarms.min_volts, Some(12.0));
+    assert_eq!(alarms.max_egt_spread, Some(500));
+    assert_eq!(alarms.max_cht, Some(500));
+    assert_eq!(alarms.max_cht_cool_rate, Some(60));
+    assert_eq!(alarms.max_egt, Some(1650));
+    assert_eq!(alarms.max_oil_temp, Some(245));
+
+    let features = data.features.unwrap();
+    assert_eq!(features.model, Some(900));
+    assert_eq!(features.firmware_version, Some(140));
+    let sensors = features.sensors.unwrap();
+    assert_eq!(sensors.egt_count, Some(4));
+    assert_eq!(sensors.cht_count, Some(4));
+    assert!(sensors.oil_pressure);
+    assert_eq!(data.flights.len(), 2);
+}
+
+#[test]
+fn four_cylinder_flights() {
+    let flights = fixture("4cyl.jpi").flights().unwrap();
+    assert_eq!(
+        flights.iter().map(|f| f.number).collect::<Vec<i32>>(),
+        [3, 4]
+    );
+    assert!(flights.iter().all(|f| f.diagnostics.is_empty()));
+    assert_eq!(flights[0].records.len(), 16);
+    assert_eq!(flights[1].records.len(), 5);
+    assert!(flights
+        .iter()
+        .all(|f| f.recording_interval_secs() == Some(6)));
+    let records = &flights[0].records;
+    assert_eq!(
+        records[1].timestamp.unwrap() - records[0].timestamp.unwrap(),
+        6
+    );
+
+    let first = &flights[0].records[0];
+    assert_eq!(first.egt, [1154, 1106, 1153, 1127]);
+    assert_eq!(first.cht, [207, 227, 223, 221]);
+    assert_eq!(first.oil_temp, Some(122));
+    assert_eq!(first.oil_pressure, Some(63));
+    assert!(flights[0]
+        .records
+        .iter()
+        .filter_map(|r| r.oil_pressure)
+        .all(|psi| (40..=90).contains(&psi)));
+    assert_eq!(first.volts, Some(14.5));
+    assert_eq!(first.rpm, Some(1268));
+    assert_eq!(first.get(Channel::FuelFlow), Some(2.7));
+}
+
+#[test]
+fn six_cylinder_file() {
+    let header = fixture("6cyl.jpi");
+    let data = header.data();
+    assert_eq!(data.registration.as_deref(), Some("N6CYL"));
+    assert_eq!(data.alarms.unwrap().max_cht, Some(460));
+    let sensors = data.features.unwrap().sensors.unwrap();
+    assert_eq!(sensors.egt_count, Some(6));
+    assert_eq!(sensors.cht_count, Some(6));
+    assert!(sensors.tit1);
+
+    let flights = header.flights().unwrap();
+    assert_eq!(flights.len(), 2);
+    assert!(flights.iter().all(|f| f.diagnostics.is_empty()));
+    assert_eq!(flights[0].records.len(), 12);
+    assert_eq!(flights[1].records.len(), 8);
+
+    let record = &flights[0].records[2];
+    assert_eq!(record.egt, [1256, 1266, 1276, 1286, 1296, 1306]);
+    assert_eq!(record.cht[5], 357);
+    assert_eq!(record.tit1, Some(1404));
+    assert_eq!(record.map, Some(24.3));
+    assert_eq!(record.rpm, Some(2460));
+}
+
+#[test]
+fn odd_length_flight() {
+    // Flight 1 is 2899 bytes, so flight 2 starts one byte inside the 1450
+    // words `$D` gives flight 1
+    let header = fixture("odd_flight.jpi");
+    let flights = header.flights().unwrap();
+    assert_eq!(
+        flights.iter().map(|f| f.number).collect::<Vec<i32>>(),
+        [1, 2]
+    );
+    assert_eq!(flights[0].words, 1450);
+    assert_eq!(flights[1].offset, flights[0].offset + 2899);
+    assert!(flights.iter().all(|f| f.diagnostics.is_empty()));
+    assert_eq!(flights[0].records.len(), 81);
+    assert_eq!(flights[1].records.len(), 77);
+    assert_eq!(flights[0].records[0].egt, [823, 806, 787, 814]);
+    assert_eq!(flights[1].records[0].egt, [1283, 1238, 1234, 1248]);
+    assert_eq!(flights[1].records[0].rpm, Some(1211));
+    assert_eq!(flights[1].records[76].egt, [949, 901, 930, 892]);
+
+    let skimmed = header.skim_flights().unwrap();
+    assert_eq!(skimmed[1].offset, flights[1].offset);
+    assert_eq!(skimmed[1].sample_count(), 77);
+}
+
+#[test]
+fn serial_number() {
+    let data = fixture("serial.jpi").data();
+    assert_eq!(data.serial_number.as_deref(), Some("900123"));
+}
+
+#[test]
+fn bad_header_checksum() {
+    let data = fixture("bad_checksum.jpi").data();
+    assert_eq!(data.registration.as_deref(), Some("N75278"));
+    assert_eq!(
+        data.diagnostics,
+        [Diagnostic::BadHeaderChecksum {
+            line: "$U, N75278*29".to_string()
+        }]
+    );
+}
+
+#[test]
+fn truncated_alarms() {
+    let mut lines = common::header_lines();
+    lines[1] = "A,155,130,500".to_string();
+    let file = common::file_with(&lines, &[common::flight(1, 3)]);
+
+    let error = file.try_data().unwrap_err();
+    assert_eq!(
+        error,
+        ParseError::FieldCount {
+            tag: 'A',
+            line: 2,
+            expected: 8,
+            found: 3
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "header line 2 ($A): expected 8 fields, found 3"
+    );
+
+    // Without try_data the line is skipped and the rest still parses
+    let data = file.data();
+    assert!(data.alarms.is_none());
+    assert_eq!(data.flights.len(), 1);
+}
+
+#[test]
+fn header_map() {
+    let map = fixture("4cyl.jpi").data().to_map();
+    assert_eq!(
+        map.keys().map(String::as_str).collect::<Vec<&str>>(),
+        [
+            "alarms.max_cht",
+            "alarms.max_cht_cool_rate",
+            "alarms.max_egt",
+            "alarms.max_egt_spread",
+            "alarms.max_oil_temp",
+            "alarms.max_volts",
+            "alarms.min_oil_temp",
+            "alarms.min_volts",
+            "download_time",
+            "firmware_version",
+            "model",
+            "protocol_version",
+            "registration",
+        ]
+    );
+    assert_eq!(map["registration"], "N75278");
+    assert_eq!(map["model"], "900");
+    assert_eq!(map["alarms.max_volts"], "15");
+    assert_eq!(map["download_time"], "2022-08-05 11:35:00");
+}
+
+#[test]
+fn known_header_length() {
+    let scanned = fixture("6cyl.jpi");
+    // The first flight's number is the first zero byte in the file
+    let header_len = scanned.file_stream.iter().position(|b| *b == 0).unwrap();
+    let bytes = scanned.file_stream.clone();
+    assert!(EdmHeader::with_header_len(bytes.clone(), header_len - 1).is_none());
+
+    let known = EdmHeader::with_header_len(bytes, header_len).unwrap();
+    assert_eq!(
+        format!("{:?}", known.data()),
+        format!("{:?}", scanned.data())
+    );
+    assert_eq!(
+        format!("{:?}", known.flights().unwrap()),
+        format!("{:?}", scanned.flights().unwrap())
+    );
+}
+
+#[test]
+fn header_from_bytes() {
+    let bytes = std::fs::read(format!(
+        "{}/tests/fixtures/6cyl.jpi",
+        env!("CARGO_MANIFEST_DIR")
+    ))
+    .unwrap();
+    let data = HeaderData::try_from(&bytes[..]).unwrap();
+    assert_eq!(data.registration.as_deref(), Some("N6CYL"));
+
+    let truncated = b"$U,N12345*3E\r\n";
+    assert_eq!(
+        HeaderData::try_from(&truncated[..]).unwrap_err(),
+        ParseError::HeaderEnd
+    );
+}
+
+#[test]
+fn missing_header_checksum() {
+    let bytes = fixture("4cyl.jpi").file_stream;
+    let at = bytes.windows(8).position(|w| w == b"$P, 2*6E").unwrap();
+    let mut bytes = [&bytes[..at], b"$P, 2", &bytes[at + 8..]].concat();
+    let data = EdmHeader::from_bytes(bytes.clone()).try_data().unwrap();
+    assert_eq!(data.protocol_version, Some(2));
+    assert_eq!(
+        data.diagnostics,
+        [Diagnostic::MissingHeaderChecksum {
+            line: "$P, 2".to_string()
+        }]
+    );
+
+    // An unreadable checksum fails like a wrong one
+    bytes.splice(at..at + 5, b"$P,2*G".to_vec());
+    let data = EdmHeader::from_bytes(bytes).data();
+    assert_eq!(
+        data.diagnostics,
+        [Diagnostic::BadHeaderChecksum {
+            line: "$P,2*G".to_string()
+        }]
+    );
+}
+
+#[test]
+fn header_diff() {
+    let before = common::file(&[common::flight(1, 2)]).data();
+    let mut lines = common::header_lines();
+    lines[1] = "A,155,130,500,440,60,1650,230,90".to_string();
+    let after = common::file_with(&lines, &[common::flight(1, 2)]).data();
+
+    assert_eq!(before.diff(&before), []);
+    assert_eq!(
+        before.diff(&after),
+        [FieldChange {
+            field: "alarms.max_cht".to_string(),
+            from: Some("460".to_string()),
+            to: Some("440".to_string()),
+        }]
+    );
+}
+
+#[test]
+fn alarm_volts_in_either_scale() {
+    let alarms = fixture("4cyl.jpi").data().alarms.unwrap();
+    assert_eq!(alarms.max_volts, Some(15.0));
+    assert_eq!(alarms.min_volts, Some(12.0));
+
+    // Older firmware writes whole volts
+    let mut lines = common::header_lines();
+    lines[1] = "A,16,13,500,460,60,1650,230,90".to_string();
+    let alarms = common::file_with(&lines, &[common::flight(1, 2)])
+        .data()
+        .alarms
+        .unwrap();
+    assert_eq!(alarms.max_volts, Some(16.0));
+    assert_eq!(alarms.min_volts, Some(13.0));
+
+    lines[1] = "A,145,118,500,460,60,1650,230,90".to_string();
+    let alarms = common::file_with(&lines, &[common::flight(1, 2)])
+        .data()
+        .alarms
+        .unwrap();
+    assert_eq!(alarms.max_volts, Some(14.5));
+    assert_eq!(alarms.min_volts, Some(11.8));
+}
+
+#[test]
+fn applied_profile() {
+    let data = fixture("4cyl.jpi").data();
+    assert_eq!(
+        data.applied_profile().to_string(),
+        "EDM-900 firmware 140: 14 word flight headers, alarm volts in tenths"
+    );
+
+    let mut lines = common::header_lines();
+    lines[1] = "A,16,13,500,460,60,1650,230,90".to_string();
+    lines[2] = lines[2].replacen("900", "760", 1);
+    let profile = common::file_with(&lines, &[common::flight(1, 2)])
+        .data()
+        .applied_profile();
+    assert!(!profile.long_flight_header);
+    assert_eq!(profile.alarm_volts_in_tenths, Some(false));
+    assert_eq!(
+        profile.to_string(),
+        "EDM-760 firmware 140: 7 word flight headers, alarm volts in whole volts"
+    );
+}
+
+#[test]
+fn header_lines_by_tag() {
+    let header = fixture("4cyl.jpi");
+    let lines = header.header_lines().unwrap();
+    assert_eq!(lines[0].tag, 'U');
+    assert_eq!(lines[0].fields, ["N75278"]);
+    assert!(lines.iter().all(|line| line.checksum == Some(true)));
+
+    let flights = header.flights().unwrap();
+    let index = header.lines_with_tag('D').unwrap().collect::<Vec<_>>();
+    assert_eq!(index.len(), flights.len());
+    assert_eq!(index[0].fields[0], flights[0].number.to_string());
+    assert!(index.windows(2).all(|w| w[0].number < w[1].number));
+    assert_eq!(header.lines_with_tag('Z').unwrap().count(), 0);
+}
+
+#[test]
+fn two_digit_download_year() {
+    let download_time = |year: &str, options: &DecodeConfig| {
+        let mut lines = common::header_lines();
+        lines.push(format!("T,8,5,{year},11,35,70"));
+        let file = common::file_with(&lines, &[common::flight(1, 2)]);
+        let time = file.try_data_with(options).unwrap().download_time;
+        time.map(jpi::format_timestamp)
+    };
+    let default = DecodeConfig::default();
+    assert_eq!(
+        download_time("99", &default).unwrap(),
+        "1999-08-05 11:35:00"
+    );
+    assert_eq!(
+        download_time("05", &default).unwrap(),
+        "2005-08-05 11:35:00"
+    );
+    assert_eq!(
+        download_time("22", &default).unwrap(),
+        "2022-08-05 11:35:00"
+    );
+
+    let options = DecodeConfig {
+        year_pivot: Some(100),
+        ..Default::default()
+    };
+    assert_eq!(
+        download_time("99", &options).unwrap(),
+        "2099-08-05 11:35:00"
+    );
+}
+
+#[test]
+fn complete_header() {
+    let data = fixture("4cyl.jpi").data();
+    assert!(data.is_complete());
+    assert_eq!(data.missing(), Vec::<&str>::new());
+    assert_eq!(fixture("bad_checksum.jpi").data().missing(), ["checksums"]);
+
+    let lines = common::header_lines()
+        .into_iter()
+        .filter(|line| !line.starts_with('C'))
+        .collect::<Vec<String>>();
+    let data = common::file_with(&lines, &[common::flight(1, 2)]).data();
+    assert!(!data.is_complete());
+    assert_eq!(data.missing(), ["config"]);
+}
+
+#[test]
+fn flight_index() {
+    let data = fixture("4cyl.jpi").data();
+    let index = data
+        .flight_index()
+        .iter()
+        .map(|flight| (flight.number, flight.words))
+        .collect::<Vec<(i32, usize)>>();
+    assert_eq!(index, [(3, 324), (4, 98)]);
+    assert!(data.flight_index().iter().all(|flight| flight.words > 0));
+}
+
+#[test]
+fn download_datetime() {
+    // $T, 8, 5, 22, 11, 35, 70 is 2022-08-05 11:35 on the instrument clock
+    let data = fixture("4cyl.jpi").data();
+    assert_eq!(data.download_time, Some(1_659_699_300));
+    assert_eq!(
+        data.download_datetime(),
+        Some(UNIX_EPOCH + Duration::from_secs(1_659_699_300))
+    );
+    assert_eq!(HeaderData::default().download_datetime(), None);
+}
+
+#[test]
+fn bare_dollar_line() {
+    let blocks = [(1, common::flight(1, 2).encode(true).unwrap())];
+    let mut bytes = common::file_bytes(&common::header_lines(), &blocks, &[]);
+    let at = bytes.windows(2).position(|w| w == b"$A").unwrap();
+    bytes.splice(at..at, *b"$\n");
+    let header = EdmHeader::from_bytes(bytes);
+
+    let data = header.try_data().unwrap();
+    assert_eq!(data.registration.as_deref(), Some("N12345"));
+    assert!(data.alarms.is_some());
+    assert_eq!(
+        data.diagnostics,
+        [Diagnostic::MissingHeaderChecksum {
+            line: "$".to_string()
+        }]
+    );
+    assert_eq!(header.flights().unwrap().len(), 1);
+}
+
+#[test]
+fn missing_config() {
+    let lines = common::header_lines()
+        .into_iter()
+        .filter(|line| !line.starts_with('C'))
+        .collect::<Vec<String>>();
+    let file = common::file_with(&lines, &[common::flight(1, 2)]);
+    assert_eq!(file.flights(), Err(ParseError::MissingConfig));
+    assert_eq!(file.skim_flights(), Err(ParseError::MissingConfig));
+}
+
+#[test]
+fn quoted_header_field() {
+    let registration = |line: &str| {
+        let mut lines = common::header_lines();
+        lines[0] = line.to_string();
+        let file = common::file_with(&lines, &[common::flight(1, 2)]);
+        let fields = file.lines_with_tag('U').unwrap().next().unwrap().fields;
+        (fields, file.data().registration)
+    };
+    assert_eq!(
+        registration("U,\"N123, A\""),
+        (vec!["N123, A".to_string()], Some("N123, A".to_string()))
+    );
+    assert_eq!(
+        registration("U,\"N1 \"\"A\"\", B\""),
+        (
+            vec!["N1 \"A\", B".to_string()],
+            Some("N1 \"A\", B".to_string())
+        )
+    );
+    // Unquoted, the comma splits the line and the registration is unknown
+    assert_eq!(registration("U,N123, A").1, None);
+}
+
+#[test]
+fn equal_parses() {
+    let first = fixture("4cyl.jpi").data();
+    let second = fixture("4cyl.jpi").data();
+    assert_eq!(first, second);
+    assert_eq!(first.clone(), first);
+    assert_eq!(
+        fixture("4cyl.jpi").flights().unwrap(),
+        fixture("4cyl.jpi").flights().unwrap()
+    );
+
+    let mut changed = first.clone();
+    changed.alarms.as_mut().unwrap().max_cht = Some(440);
+    assert_ne!(changed, first);
+}
+
+#[test]
+fn lossy_registration() {
+    let mut bytes = fixture("4cyl.jpi").file_stream;
+    let at = bytes.windows(6).position(|w| w == b"N75278").unwrap();
+    bytes[at + 3] = 0xff;
+    let header = EdmHeader::from_bytes(bytes.clone());
+    let data = header.try_data().unwrap();
+    assert_eq!(data.registration.as_deref(), Some("N75\u{fffd}78"));
+    assert!(matches!(
+        data.diagnostics[..],
+        [Diagnostic::BadHeaderChecksum { .. }]
+    ));
+    assert_eq!(header.flights().unwrap().len(), 2);
+
+    // Anywhere else a bad byte is still an error
+    bytes[at + 3] = b'2';
+    let at = bytes.windows(3).position(|w| w == b"$P,").unwrap();
+    bytes[at + 3] = 0xff;
+    let header = EdmHeader::from_bytes(bytes);
+    assert_eq!(header.try_data().unwrap_err(), ParseError::HeaderEncoding);
+    assert_eq!(
+        header.skim_flights().unwrap_err(),
+        ParseError::HeaderEncoding
+    );
+    assert_eq!(
+        header.first_records().unwrap_err(),
+        ParseError::HeaderEncoding
+    );
+}
+
+/// The header end search `EdmHeader` used to make: the first newline not
+/// followed by `$`, looked for over the whole file.
+fn scan_whole_file(bytes: &[u8]) -> Option<usize> {
+    bytes.windows(2).position(|v| v[0] == b'\n' && v[1] != b'$')
+}
+
+/// Times parsing the header of a 64 MB file against `scan_whole_file`, and
+/// checks both find the same split. Run with
+/// `cargo test --release -- --ignored --nocapture header_end_timing`.
+#[test]
+#[ignore]
+fn header_end_timing() {
+    let time = |what: &str, f: &dyn Fn()| {
+        let start = Instant::now();
+        for _ in 0..10 {
+            f();
+        }
+        eprintln!("{what}: {:?} a run", start.elapsed() / 10);
+    };
+
+    let mut bytes = fixture("6cyl.jpi").file_stream;
+    bytes.resize(64 << 20, 0x55);
+    let end = scan_whole_file(&bytes).unwrap();
+    let known = EdmHeader::with_header_len(bytes.clone(), end + 1).unwrap();
+    let header = EdmHeader::from_bytes(bytes);
+    assert_eq!(
+        format!("{:?}", header.data()),
+        format!("{:?}", known.data())
+    );
+    // The old scan alone, against parsing the whole header
+    time("header, old scan", &|| {
+        assert!(scan_whole_file(&header.file_stream).is_some())
+    });
+    time("header, parse", &|| assert!(header.try_data().is_ok()));
+
+    // With no header end the old scan read every byte before giving up
+    let binary = EdmHeader::from_bytes(vec![0x55; 64 << 20]);
+    assert_eq!(scan_whole_file(&binary.file_stream), None);
+    assert_eq!(binary.try_data().unwrap_err(), ParseError::HeaderEnd);
+    time("binary, old scan", &|| {
+        assert!(scan_whole_file(&binary.file_stream).is_none())
+    });
+    time("binary, parse", &|| assert!(binary.try_data().is_err()));
+}
+
+#[test]
+fn header_end_in_large_file() {
+    // A header in front of tens of MB of data is found without reading them
+    let mut bytes = fixture("6cyl.jpi").file_stream;
+    let expected = format!("{:?}", EdmHeader::from_bytes(bytes.clone()).data());
+    bytes.resize(32 << 20, 0x55);
+    assert_eq!(
+        format!("{:?}", EdmHeader::from_bytes(bytes).data()),
+        expected
+    );
+
+    // Without a header line to start from there is nothing to find
+    let binary = vec![0x55; 32 << 20];
+    assert_eq!(
+        HeaderData::try_from(&binary[..]).unwrap_err(),
+        ParseError::HeaderEnd
+    );
+}
+
+#[test]
+fn byte_order_mark() {
+    let bytes = fixture("4cyl.jpi").file_stream;
+    let expected = EdmHeader::from_bytes(bytes.clone());
+    for prefix in [&b"\xef\xbb\xbf"[..], b"\r\n  ", b"\xef\xbb\xbf\n"] {
+        let header = EdmHeader::from_bytes([prefix, &bytes[..]].concat());
+        let data = header.try_data().unwrap();
+        assert_eq!(data.registration.as_deref(), Some("N75278"));
+        assert!(data.diagnostics.is_empty());
+        let flights = header.flights().unwrap();
+        let expected = expected.flights().unwrap();
+        assert_eq!(flights[0].offset, expected[0].offset + prefix.len());
+        assert_eq!(
+            format!("{:?}", flights[1].records),
+            format!("{:?}", expected[1].records)
+        );
+    }
+}
+
+#[test]
+fn model_names() {
+    let features = fixture("4cyl.jpi").data().features.unwrap();
+    assert_eq!(features.model_name().as_deref(), Some("EDM-900"));
+
+    let name = |model| {
+        Features {
+            model,
+            ..Default::default()
+        }
+        .model_name()
+    };
+    assert_eq!(name(Some(830)).as_deref(), Some("EDM-830"));
+    assert_eq!(name(Some(43)).as_deref(), Some("43"));
+    assert_eq!(name(None), None);
+}
+
+#[test]
+fn strict_tags() {
+    let mut lines = common::header_lines();
+    lines.insert(1, "X,1,2".to_string());
+    let file = common::file_with(&lines, &[common::flight(1, 3)]);
+    assert!(file.try_data().is_ok());
+    assert_eq!(file.flights().unwrap().len(), 1);
+
+    let options = DecodeConfig {
+        strict_tags: true,
+        ..Default::default()
+    };
+    let error = ParseError::UnknownTag { tag: 'X', line: 2 };
+    assert_eq!(file.try_data_with(&options).unwrap_err(), error);
+    assert_eq!(file.flights_with(&options).unwrap_err(), error);
+    assert_eq!(error.to_string(), "header line 2: unknown tag $X");
+
+    // Every tag in a real download is known
+    assert!(fixture("4cyl.jpi").flights_with(&options).is_ok());
+}
+
+#[test]
+fn duplicate_header_lines() {
+    let mut lines = common::header_lines();
+    lines.insert(2, "A,155,130,500,440,60,1650,230,90".to_string());
+    let file = common::file_with(&lines, &[common::flight(1, 3)]);
+
+    let data = file.try_data().unwrap();
+    assert_eq!(data.alarms.unwrap().max_cht, Some(460));
+    assert_eq!(
+        data.diagnostics,
+        [Diagnostic::DuplicateHeaderLine { tag: 'A', line: 3 }]
+    );
+
+    let options = DecodeConfig {
+        reject_conflicting_lines: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        file.try_data_with(&options).unwrap_err(),
+        ParseError::ConflictingLine { tag: 'A', line: 3 }
+    );
+
+    // A line that is repeated exactly does not conflict
+    lines[2] = lines[1].clone();
+    let file = common::file_with(&lines, &[common::flight(1, 3)]);
+    let data = file.try_data_with(&options).unwrap();
+    assert_eq!(data.diagnostics.len(), 1);
+    assert_eq!(data.flights.len(), 1);
+}
+
+#[test]
+fn normalized_registration() {
+    let normalized = |registration: &str, strip| {
+        HeaderData {
+            registration: Some(registration.to_string()),
+            ..Default::default()
+        }
+        .registration_normalized(strip)
+    };
+    assert_eq!(normalized("N123AB", true).as_deref(), Some("123AB"));
+    assert_eq!(normalized(" n123ab", false).as_deref(), Some("N123AB"));
+    assert_eq!(normalized("VH-ABC", true).as_deref(), Some("ABC"));
+    assert_eq!(normalized("VH-ABC", false).as_deref(), Some("VH-ABC"));
+    assert_eq!(normalized("G-ABCD", true).as_deref(), Some("ABCD"));
+    // Not a US mark, and no hyphen to find the prefix by
+    assert_eq!(normalized("NOTAIL", true).as_deref(), Some("NOTAIL"));
+    assert_eq!(HeaderData::default().registration_normalized(true), None);
+}
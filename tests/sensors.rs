@@ -0,0 +1,62 @@
+use jpi::{Channel, EdmHeader, ExportError, Sensors};
+
+#[test]
+fn channel_count_sums_probes_and_channels() {
+    let sensors = Sensors {
+        egt_count: Some(6),
+        cht_count: Some(6),
+        volts: true,
+        oil_temp: true,
+        fuel_flow: true,
+        ..Default::default()
+    };
+    assert_eq!(sensors.channel_count(), 15);
+    assert_eq!(Sensors::default().channel_count(), 0);
+}
+
+#[test]
+fn select_channels() {
+    let sensors = Sensors::from_flags(1 | 0x3c | 0x7800 | 1 << 27);
+    assert_eq!(
+        sensors.select_channels(&["FF", "cht", "EGT2"]).unwrap(),
+        [
+            Channel::Egt(2),
+            Channel::Cht(1),
+            Channel::Cht(2),
+            Channel::Cht(3),
+            Channel::Cht(4),
+            Channel::FuelFlow,
+        ]
+    );
+    assert_eq!(
+        sensors.select_channels(&["MAP"]).unwrap_err(),
+        ExportError::UnknownColumn {
+            name: "MAP".to_string(),
+            available: sensors.field_labels(),
+        }
+    );
+}
+
+#[test]
+fn present_channels_from_config() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/4cyl.jpi");
+    let sensors = EdmHeader::new(path)
+        .unwrap()
+        .data()
+        .features
+        .and_then(|f| f.sensors)
+        .unwrap();
+    let mut expected = (1..=4).map(Channel::Egt).collect::<Vec<Channel>>();
+    expected.extend((1..=4).map(Channel::Cht));
+    expected.extend([
+        Channel::OilTemp,
+        Channel::OilPressure,
+        Channel::Volts,
+        Channel::Oat,
+        Channel::FuelUsed,
+        Channel::FuelFlow,
+        Channel::Map,
+        Channel::Rpm,
+    ]);
+    assert_eq!(sensors.present(), expected);
+}
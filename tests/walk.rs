@@ -0,0 +1,104 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use jpi::{walk_dir, walk_dir_for_tail, walk_dir_with, HeaderData, ParseError};
+
+fn registrations(
+    root: &Path,
+    found: impl Iterator<Item = (PathBuf, Result<HeaderData, ParseError>)>,
+) -> Vec<(PathBuf, Result<String, ParseError>)> {
+    found
+        .map(|(path, header)| {
+            let path = path.strip_prefix(root).unwrap().to_path_buf();
+            let registration = header.map(|h| h.registration.unwrap_or_default());
+            (path, registration)
+        })
+        .collect()
+}
+
+#[test]
+fn walk_archive() {
+    let root = std::env::temp_dir().join(format!("jpi-walk-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("2022")).unwrap();
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    fs::copy(fixtures.join("6cyl.jpi"), root.join("6cyl.jpi")).unwrap();
+    fs::copy(fixtures.join("4cyl.jpi"), root.join("2022/4CYL.JPI")).unwrap();
+    fs::copy(fixtures.join("serial.jpi"), root.join("2022/serial.DAT")).unwrap();
+    fs::write(root.join("2022/empty.jpi"), b"$U,N12345*3E\r\n").unwrap();
+    fs::write(root.join("notes.txt"), b"not a download").unwrap();
+
+    let found = registrations(&root, walk_dir(&root));
+    let custom = registrations(&root, walk_dir_with(&root, &["TXT", "dat"]));
+    fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(
+        found,
+        [
+            (PathBuf::from("2022/4CYL.JPI"), Ok("N75278".to_string())),
+            (PathBuf::from("2022/empty.jpi"), Err(ParseError::HeaderEnd)),
+            (PathBuf::from("2022/serial.DAT"), Ok("N75278".to_string())),
+            (PathBuf::from("6cyl.jpi"), Ok("N6CYL".to_string())),
+        ]
+    );
+    assert_eq!(
+        custom,
+        [
+            (PathBuf::from("2022/serial.DAT"), Ok("N75278".to_string())),
+            (PathBuf::from("notes.txt"), Err(ParseError::HeaderEnd)),
+        ]
+    );
+}
+
+#[test]
+fn walk_one_tail() {
+    let root = std::env::temp_dir().join(format!("jpi-tail-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("2022")).unwrap();
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    fs::copy(fixtures.join("6cyl.jpi"), root.join("6cyl.jpi")).unwrap();
+    fs::copy(fixtures.join("4cyl.jpi"), root.join("2022/4cyl.jpi")).unwrap();
+    fs::copy(fixtures.join("serial.jpi"), root.join("2022/serial.dat")).unwrap();
+    fs::write(root.join("2022/empty.jpi"), b"$U,N75278*3E\r\n").unwrap();
+
+    let found = registrations(&root, walk_dir_for_tail(&root, "n75278"));
+    let other = registrations(&root, walk_dir_for_tail(&root, "N6CYL"));
+    let none = registrations(&root, walk_dir_for_tail(&root, "N1"));
+    fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(
+        found,
+        [
+            (PathBuf::from("2022/4cyl.jpi"), Ok("N75278".to_string())),
+            (PathBuf::from("2022/serial.dat"), Ok("N75278".to_string())),
+        ]
+    );
+    assert_eq!(
+        other,
+        [(PathBuf::from("6cyl.jpi"), Ok("N6CYL".to_string()))]
+    );
+    assert!(none.is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn walk_symlink_loop() {
+    let root = std::env::temp_dir().join(format!("jpi-loop-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("2022")).unwrap();
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    fs::copy(fixtures.join("4cyl.jpi"), root.join("2022/4cyl.jpi")).unwrap();
+    // A link back to the root, and a second way into 2022
+    std::os::unix::fs::symlink(&root, root.join("2022/up")).unwrap();
+    std::os::unix::fs::symlink(root.join("2022"), root.join("again")).unwrap();
+
+    let found = registrations(&root, walk_dir(&root));
+    fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(
+        found,
+        [(PathBuf::from("2022/4cyl.jpi"), Ok("N75278".to_string()))]
+    );
+}